@@ -4,8 +4,12 @@ document.addEventListener('DOMContentLoaded', () => {
     const dirPathInput = document.getElementById('dir-path');
     const fileTreeContainer = document.getElementById('file-tree-container');
     const ingestBtn = document.getElementById('ingest-btn');
+    const uploadForm = document.getElementById('upload-form');
+    const uploadFilesInput = document.getElementById('upload-files');
+    const uploadBtn = document.getElementById('upload-btn');
     const ragForm = document.getElementById('rag-form');
     const questionInput = document.getElementById('question');
+    const languageFilter = document.getElementById('language-filter');
     const ragBtn = document.getElementById('rag-btn');
     const answerContainer = document.getElementById('answer-container');
     const copyBtn = document.getElementById('copy-btn');
@@ -28,6 +32,7 @@ document.addEventListener('DOMContentLoaded', () => {
     // --- Funciones de Utilidad ---
     function setBusy(isBusy, message) {
         ingestBtn.disabled = isBusy || !document.querySelector('.node-selected');
+        uploadBtn.disabled = isBusy;
         ragBtn.disabled = isBusy;
         questionInput.disabled = isBusy;
         dirForm.querySelector('button').disabled = isBusy;
@@ -210,6 +215,70 @@ document.addEventListener('DOMContentLoaded', () => {
         }
     });
 
+    // Sube los ficheros elegidos al servidor; la ingesta sigue como la de un directorio.
+    uploadForm.addEventListener('submit', async (e) => {
+        e.preventDefault();
+        if (!uploadFilesInput.files.length) return;
+        const formData = new FormData();
+        for (const file of uploadFilesInput.files) formData.append('files', file, file.name);
+        setBusy(true, `Subiendo ${uploadFilesInput.files.length} fichero(s)...`);
+        try {
+            const response = await fetch(`${API_BASE}/upload`, { method: 'POST', body: formData });
+            const data = await response.json();
+            if (!response.ok) throw new Error(data.error || 'No se pudo subir.');
+            uploadForm.reset();
+            setBusy(true, 'Ficheros subidos. Iniciando indexación...');
+            if (statusInterval) clearInterval(statusInterval);
+            statusInterval = setInterval(fetchStatus, 500);
+        } catch (error) {
+            console.error(error);
+            setBusy(false, `Error: ${error.message}`);
+        }
+    });
+
+    // Muestra (o esconde) el pasaje original de una fuente resaltado dentro de su contexto.
+    async function toggleSourceContext(li, chunkId) {
+        const existing = li.querySelector('.source-context');
+        if (existing) {
+            existing.remove();
+            return;
+        }
+        const box = document.createElement('div');
+        box.className = 'source-context';
+        box.textContent = 'Cargando contexto...';
+        li.appendChild(box);
+        try {
+            const response = await fetch(`${API_BASE}/chunks/${encodeURIComponent(chunkId)}/context`);
+            const data = await response.json();
+            if (!response.ok) throw new Error(data.error || 'Error al cargar el contexto.');
+            box.textContent = '';
+            if (data.line_start) {
+                const lines = document.createElement('div');
+                lines.className = 'source-lines';
+                lines.textContent = `Líneas ${data.line_start}–${data.line_end}`;
+                box.appendChild(lines);
+            }
+            const pre = document.createElement('pre');
+            if (data.window) {
+                const mark = document.createElement('mark');
+                mark.textContent = data.window.passage;
+                pre.append(data.window.before, mark, data.window.after);
+            } else {
+                // El fichero cambió desde la ingesta: sólo tenemos el texto del chunk.
+                pre.textContent = data.text;
+            }
+            box.appendChild(pre);
+            scrollToMark(pre);
+        } catch (error) {
+            box.textContent = `Error: ${error.message}`;
+        }
+    }
+
+    function scrollToMark(pre) {
+        const mark = pre.querySelector('mark');
+        if (mark) pre.scrollTop = Math.max(0, mark.offsetTop - pre.offsetTop - 40);
+    }
+
     ragForm.addEventListener('submit', async (e) => {
         e.preventDefault();
         const question = questionInput.value.trim();
@@ -220,13 +289,13 @@ document.addEventListener('DOMContentLoaded', () => {
         keyEntitiesContainer.innerHTML = '';
 
         try {
-            const response = await fetch(`${API_BASE}/rag-query`, { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: JSON.stringify({ question }), });
+            const response = await fetch(`${API_BASE}/rag-query`, { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: JSON.stringify({ question, language: languageFilter.value || null }), });
             if (!response.ok) {
                 const err = await response.json();
                 throw new Error(err.error || 'Error en la consulta RAG.');
             }
             
-            const { answer, key_entities } = await response.json();
+            const { answer, key_entities, sources } = await response.json();
 
             answerContainer.innerHTML = ''; 
             const paragraphs = answer.split(/\n\s*\n/); 
@@ -250,6 +319,32 @@ document.addEventListener('DOMContentLoaded', () => {
                 });
                 keyEntitiesContainer.appendChild(ul);
             }
+
+            if (sources && sources.length > 0) {
+                const h4 = document.createElement('h4');
+                h4.textContent = 'Fuentes:';
+                keyEntitiesContainer.appendChild(h4);
+                const ul = document.createElement('ul');
+                sources.forEach(source => {
+                    const li = document.createElement('li');
+                    const parts = [source.document || source.source || 'Documento'];
+                    if (source.section) parts.push(source.section);
+                    if (source.language && source.language !== 'und') parts.push(source.language.toUpperCase());
+                    if (source.page_start) {
+                        parts.push(source.page_end && source.page_end !== source.page_start
+                            ? `págs. ${source.page_start}–${source.page_end}`
+                            : `pág. ${source.page_start}`);
+                    }
+                    li.textContent = parts.join(' · ');
+                    li.title = source.source || '';
+                    if (source.chunk_id) {
+                        li.classList.add('source-item');
+                        li.addEventListener('click', () => toggleSourceContext(li, source.chunk_id));
+                    }
+                    ul.appendChild(li);
+                });
+                keyEntitiesContainer.appendChild(ul);
+            }
             setBusy(false, 'Consulta RAG completada.');
 
         } catch (error) {
@@ -1,7 +1,11 @@
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use anyhow::anyhow;
 use axum::{
-    extract::{Json, State},
-    http::StatusCode,
+    extract::{rejection::JsonRejection, DefaultBodyLimit, Json, Multipart, Path as AxumPath, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     routing::{get, post},
     Router,
@@ -9,13 +13,18 @@ use axum::{
 use neo4rs::{query, Node, Relation};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::spawn;
-use tracing::{error, info};
+use tokio::{io::AsyncWriteExt, spawn};
+use tracing::{error, info, warn};
 use url::Url;
+use uuid::Uuid;
 
 use crate::{
     app_state::{AppState, Status},
-    ingest, models::FileTreeNode, rag,
+    file_filter::{FileFilter, FilterRules},
+    git, ingest,
+    jobs::JobInfo,
+    language,
+    models::FileTreeNode, provenance, rag, watcher,
 };
 
 // --- Payloads y Respuestas de la API (MODIFICADO) ---
@@ -25,16 +34,43 @@ pub struct SelectDirPayload {
     path: String,
 }
 
-#[derive(Deserialize)]
-pub struct RagQueryPayload {
-    question: String,
+/// Cuerpo opcional de `/api/ingest`: sustituye los filtros de la configuración
+/// y, en un repositorio git, limita la ingesta a lo cambiado entre dos revisiones.
+#[derive(Deserialize, Default)]
+pub struct IngestPayload {
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    max_file_size_bytes: Option<u64>,
+    respect_gitignore: Option<bool>,
+    skip_hidden: Option<bool>,
+    /// Revisión de partida: sólo se re-ingieren los ficheros cambiados desde ella.
+    since: Option<String>,
+    /// Revisión final: `HEAD` (el valor por defecto) o una que apunte a su
+    /// mismo commit, porque los ficheros se leen de la copia de trabajo.
+    until: Option<String>,
 }
 
-// MEJORA: La respuesta ahora incluye la respuesta y las entidades clave.
+/// Respuesta de `/api/upload`: los ficheros ya están guardados y la ingesta
+/// sigue en el trabajo `job_id` (`/api/jobs/:id` da sus `:Document` al terminar).
 #[derive(Serialize)]
-pub struct RagQueryResponse {
-    answer: String,
-    key_entities: Vec<String>,
+pub struct UploadResponse {
+    job_id: String,
+    /// Rutas en las que se guardaron los ficheros subidos.
+    files: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+pub struct ChunkContextParams {
+    /// Caracteres de contexto a cada lado del pasaje.
+    window: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct RagQueryPayload {
+    question: String,
+    /// Sólo usar chunks en este idioma (ISO 639-1 o 639-3, p. ej. `en`).
+    #[serde(default)]
+    language: Option<String>,
 }
 
 // MEJORA: Estructura para la lista de entidades.
@@ -69,11 +105,18 @@ pub struct GraphData {
 // --- Router ---
 
 pub fn create_router(app_state: AppState) -> Router {
+    let upload_limit = DefaultBodyLimit::max(app_state.config.upload_max_bytes);
     Router::new()
         .route("/api/list-directory", post(list_directory_handler))
         .route("/api/select-directory", post(select_directory_handler))
         .route("/api/ingest", post(ingest_handler))
+        .route("/api/upload", post(upload_handler).layer(upload_limit))
+        .route("/api/jobs", get(list_jobs_handler))
+        .route("/api/jobs/:id", get(get_job_handler).delete(cancel_job_handler))
+        .route("/api/prune", post(prune_handler))
+        .route("/api/watch", post(start_watch_handler).delete(stop_watch_handler))
         .route("/api/rag-query", post(rag_query_handler))
+        .route("/api/chunks/:id/context", get(chunk_context_handler))
         .route("/api/status", get(status_handler))
         .route("/api/neo4j-info", get(neo4j_info_handler))
         .route("/api/shutdown", post(shutdown_handler))
@@ -138,6 +181,8 @@ async fn select_directory_handler(
 #[axum::debug_handler]
 async fn ingest_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Result<Json<IngestPayload>, JsonRejection>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     let root_dir = match state.current_dir.lock().unwrap().clone() {
         Some(dir) => dir,
@@ -148,59 +193,453 @@ async fn ingest_handler(
             ));
         }
     };
-    
-    spawn(async move {
-        {
-            let mut status = state.status.lock().unwrap();
-            status.is_busy = true;
-            status.message = "Iniciando indexación...".to_string();
-            status.progress = 0.0;
+
+    // Sólo una petición sin cuerpo usa los filtros de la configuración; un cuerpo
+    // mal formado (p. ej. un `since` que no es texto) se rechaza.
+    let mut payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(JsonRejection::MissingJsonContentType(_)) if !has_body(&headers) => IngestPayload::default(),
+        Err(rejection) => {
+            return Err((
+                rejection.status(),
+                Json(json!({"error": format!("Cuerpo de la petición inválido: {}", rejection.body_text())})),
+            ));
+        }
+    };
+    let revisions = match (payload.since.take(), payload.until.take()) {
+        (Some(since), until) => Some((since, until.unwrap_or_else(|| "HEAD".to_string()))),
+        (None, Some(_)) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "'until' requiere indicar también 'since'."})),
+            ));
+        }
+        (None, None) => None,
+    };
+    let options = ingest_options(&state, payload).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("Filtros de ingesta inválidos: {}", e)})),
+        )
+    })?;
+
+    // Con `since`, sólo los ficheros que git da por cambiados entre las dos revisiones.
+    let changed_paths = match revisions {
+        Some((since, until)) => {
+            let root = root_dir.clone();
+            let paths = tokio::task::spawn_blocking(move || git::changed_paths(&root, &since, &until))
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|result| result)
+                .map_err(|e| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({"error": format!("No se pudieron obtener los cambios de git: {}", e)})),
+                    )
+                })?;
+            Some(paths)
+        }
+        None => None,
+    };
+
+    let (job_id, cancel) = state.jobs.start(root_dir.clone()).map_err(|running_id| {
+        (
+            StatusCode::CONFLICT,
+            Json(json!({"error": "Ya hay una indexación en curso.", "job_id": running_id})),
+        )
+    })?;
+
+    let task_state = state.clone();
+    let task_cancel = cancel.clone();
+    spawn(run_ingest_job(state, job_id.clone(), cancel, async move {
+        let state = task_state;
+        begin_ingest_status(&state);
+
+        match &changed_paths {
+            Some(paths) => {
+                ingest::sync_paths(
+                    &state.graph,
+                    &state.llm_manager,
+                    &root_dir,
+                    paths,
+                    &options,
+                    state.status.clone(),
+                    &task_cancel,
+                ).await
+            }
+            None => {
+                ingest::ingest_directory(
+                    &state.graph,
+                    &state.llm_manager,
+                    &root_dir,
+                    &options,
+                    state.status.clone(),
+                    &task_cancel,
+                ).await
+            }
+        }
+    }));
+
+    Ok((StatusCode::ACCEPTED, Json(json!({ "job_id": job_id }))))
+}
+
+/// Si la petición trae cuerpo, según sus cabeceras.
+fn has_body(headers: &HeaderMap) -> bool {
+    let length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    length.is_some_and(|length| length > 0) || headers.contains_key(header::TRANSFER_ENCODING)
+}
+
+/// Recibe uno o varios ficheros (también archivos comprimidos) en un formulario
+/// multipart, los guarda en un directorio nuevo dentro de `UPLOAD_DIR` y los
+/// ingiere como cualquier otro directorio. Responde `202` en cuanto los ficheros
+/// están en disco; los `:Document` creados se consultan en `/api/jobs/:id`.
+#[axum::debug_handler]
+async fn upload_handler(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let internal_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Error al procesar la subida: {}", e)})),
+        )
+    };
+
+    let upload_dir = Path::new(&state.config.upload_dir);
+    let batch_dir = tokio::fs::create_dir_all(upload_dir)
+        .await
+        .and_then(|_| std::fs::canonicalize(upload_dir))
+        .map(|dir| dir.join(Uuid::new_v4().to_string()))
+        .map_err(|e| internal_error(e.into()))?;
+
+    // Los ficheros subidos no están bajo ningún repositorio ni `.gitignore` propio.
+    let mut options = ingest::IngestOptions::from_config(&state.config, state.llm_permits.clone()).map_err(internal_error)?;
+    let mut rules = FilterRules::from_config(&state.config);
+    rules.respect_gitignore = false;
+    options.filter = FileFilter::new(&rules).map_err(internal_error)?;
+    options.git_metadata = false;
+
+    // El trabajo se registra con los ficheros ya en disco: una subida lenta no
+    // bloquea otras ingestas ni deja un trabajo abierto si se corta.
+    let files = match save_uploads(&mut multipart, &batch_dir).await {
+        Ok(files) => files,
+        Err(e) => {
+            remove_upload_dir(&batch_dir).await;
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": format!("Subida inválida: {}", e)})),
+            ));
+        }
+    };
+    info!("{} ficheros subidos a {}", files.len(), batch_dir.display());
+
+    let (job_id, cancel) = match state.jobs.start(batch_dir.clone()) {
+        Ok(job) => job,
+        Err(running_id) => {
+            remove_upload_dir(&batch_dir).await;
+            return Err((
+                StatusCode::CONFLICT,
+                Json(json!({"error": "Ya hay una indexación en curso.", "job_id": running_id})),
+            ));
         }
+    };
 
-        let result = ingest::ingest_directory(
+    let task_state = state.clone();
+    let task_job_id = job_id.clone();
+    let task_cancel = cancel.clone();
+    spawn(run_ingest_job(state, job_id.clone(), cancel, async move {
+        let state = task_state;
+        begin_ingest_status(&state);
+        let summary = ingest::ingest_directory(
             &state.graph,
             &state.llm_manager,
-            &root_dir,
+            &batch_dir,
+            &options,
             state.status.clone(),
-        ).await;
+            &task_cancel,
+        ).await?;
+        // Antes de cerrar el trabajo, para que quien lo consulte los vea al terminar.
+        let document_ids = ingest::document_ids_under(&state.graph, &batch_dir).await?;
+        state.jobs.set_document_ids(&task_job_id, document_ids);
+        Ok(summary)
+    }));
+
+    Ok((StatusCode::ACCEPTED, Json(UploadResponse { job_id, files })))
+}
 
-        let mut status = state.status.lock().unwrap();
-        status.is_busy = false;
-        status.progress = 0.0;
-        match result {
-            Ok(summary) => {
-                status.message = format!("¡Indexación completada! {}", summary);
-            }
-            Err(err) => {
-                status.message = format!("Error en la indexación: {}", err);
-                error!("Error de ingesta: {}", err);
-            }
+/// Borra el directorio de una subida que no llega a ingerirse.
+async fn remove_upload_dir(dir: &Path) {
+    if let Err(e) = tokio::fs::remove_dir_all(dir).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("No se pudo borrar {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Guarda en `dir`, por trozos y sin cargarlos enteros en memoria, los ficheros
+/// del formulario. Del nombre sólo se conserva la última componente; los
+/// campos sin nombre de fichero se ignoran.
+async fn save_uploads(multipart: &mut Multipart, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    tokio::fs::create_dir_all(dir).await?;
+    let mut saved: Vec<PathBuf> = Vec::new();
+    while let Some(mut field) = multipart.next_field().await? {
+        let Some(name) = field
+            .file_name()
+            .and_then(|name| Path::new(&name.replace('\\', "/")).file_name().map(|n| n.to_string_lossy().to_string()))
+        else {
+            continue;
+        };
+
+        let mut path = dir.join(&name);
+        let mut copy = 1;
+        while saved.contains(&path) {
+            path = dir.join(format!("{copy}-{name}"));
+            copy += 1;
+        }
+
+        let mut file = tokio::fs::File::create(&path).await?;
+        while let Some(bytes) = field.chunk().await? {
+            file.write_all(&bytes).await?;
+        }
+        file.flush().await?;
+        saved.push(path);
+    }
+
+    if saved.is_empty() {
+        return Err(anyhow!("la petición no contiene ningún fichero"));
+    }
+    Ok(saved)
+}
+
+/// Ejecuta `ingest` en su propia tarea y cierra el trabajo con su resultado.
+/// Si la tarea entra en pánico, el trabajo queda como fallido en lugar de
+/// seguir en ejecución y bloquear las ingestas siguientes con un 409.
+async fn run_ingest_job<F>(
+    state: AppState,
+    job_id: String,
+    cancel: Arc<AtomicBool>,
+    ingest: F,
+) -> anyhow::Result<ingest::IngestionSummary>
+where
+    F: Future<Output = anyhow::Result<ingest::IngestionSummary>> + Send + 'static,
+{
+    let result = spawn(ingest)
+        .await
+        .map_err(|e| anyhow!("La tarea de ingesta terminó de forma inesperada: {}", e))
+        .and_then(|result| result);
+    finish_ingest_job(&state, &job_id, &cancel, &result);
+    result
+}
+
+/// Marca el estado global como ocupado al empezar una ingesta.
+fn begin_ingest_status(state: &AppState) {
+    let mut status = state.status.lock().unwrap();
+    status.is_busy = true;
+    status.message = "Iniciando indexación...".to_string();
+    status.progress = 0.0;
+}
+
+/// Cierra el trabajo con el resultado de la ingesta y lo refleja en el estado global.
+fn finish_ingest_job(
+    state: &AppState,
+    job_id: &str,
+    cancel: &AtomicBool,
+    result: &anyhow::Result<ingest::IngestionSummary>,
+) {
+    state.jobs.finish(job_id, result);
+
+    // El estado puede haber quedado envenenado por el pánico de la propia ingesta.
+    let mut status = state.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    status.is_busy = false;
+    status.progress = 0.0;
+    match result {
+        Ok(summary) if cancel.load(Ordering::Relaxed) => {
+            status.message = format!("Indexación cancelada. {}", summary);
+        }
+        Ok(summary) => {
+            status.message = format!("¡Indexación completada! {}", summary);
+        }
+        Err(err) => {
+            status.message = format!("Error en la indexación: {}", err);
+            error!("Error de ingesta: {}", err);
+        }
+    }
+}
+
+/// Opciones de la configuración con los filtros que traiga la petición.
+fn ingest_options(state: &AppState, payload: IngestPayload) -> anyhow::Result<ingest::IngestOptions> {
+    let mut options = ingest::IngestOptions::from_config(&state.config, state.llm_permits.clone())?;
+    let mut rules = FilterRules::from_config(&state.config);
+    if let Some(include) = payload.include {
+        rules.include = include;
+    }
+    if let Some(exclude) = payload.exclude {
+        rules.exclude = exclude;
+    }
+    if let Some(max_file_size_bytes) = payload.max_file_size_bytes {
+        rules.max_file_size_bytes = max_file_size_bytes;
+    }
+    if let Some(respect_gitignore) = payload.respect_gitignore {
+        rules.respect_gitignore = respect_gitignore;
+    }
+    if let Some(skip_hidden) = payload.skip_hidden {
+        rules.skip_hidden = skip_hidden;
+    }
+    options.filter = FileFilter::new(&rules)?;
+    Ok(options)
+}
+
+#[axum::debug_handler]
+async fn list_jobs_handler(State(state): State<AppState>) -> Json<Vec<JobInfo>> {
+    Json(state.jobs.list())
+}
+
+#[axum::debug_handler]
+async fn get_job_handler(
+    State(state): State<AppState>,
+    AxumPath(job_id): AxumPath<String>,
+) -> Result<Json<JobInfo>, (StatusCode, Json<serde_json::Value>)> {
+    state.jobs.get(&job_id).map(Json).ok_or_else(|| job_not_found(&job_id))
+}
+
+/// Solicita la cancelación de un trabajo; se detiene entre ficheros.
+#[axum::debug_handler]
+async fn cancel_job_handler(
+    State(state): State<AppState>,
+    AxumPath(job_id): AxumPath<String>,
+) -> Result<(StatusCode, Json<JobInfo>), (StatusCode, Json<serde_json::Value>)> {
+    let job = state.jobs.cancel(&job_id).ok_or_else(|| job_not_found(&job_id))?;
+    info!("Cancelación solicitada para el trabajo {}", job_id);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+fn job_not_found(job_id: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({"error": format!("No existe el trabajo {}", job_id)})),
+    )
+}
+
+
+/// Reconciliación manual: elimina del grafo los ficheros del directorio
+/// seleccionado que ya no existen en disco. Se registra como un trabajo más
+/// y responde 409 si hay otro en marcha.
+#[axum::debug_handler]
+async fn prune_handler(
+    State(state): State<AppState>,
+) -> Result<Json<ingest::IngestionSummary>, (StatusCode, Json<serde_json::Value>)> {
+    let root_dir = match state.current_dir.lock().unwrap().clone() {
+        Some(dir) => dir,
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Primero debe seleccionar un directorio."})),
+            ));
+        }
+    };
+
+    // Como una ingesta: no se borran subárboles mientras otro trabajo los reescribe.
+    let (job_id, _cancel) = state.jobs.start(root_dir.clone()).map_err(|running_id| {
+        (
+            StatusCode::CONFLICT,
+            Json(json!({"error": "Ya hay una indexación en curso.", "job_id": running_id})),
+        )
+    })?;
+    let graph = state.graph.clone();
+    let result = spawn(async move { ingest::prune_missing_files(&graph, &root_dir).await })
+        .await
+        .map_err(|e| anyhow!("La tarea de reconciliación terminó de forma inesperada: {}", e))
+        .and_then(|result| result);
+    state.jobs.finish(&job_id, &result);
+
+    match result {
+        Ok(summary) => Ok(Json(summary)),
+        Err(e) => {
+            error!("Error en la reconciliación: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Error al eliminar ficheros desaparecidos: {}", e)})),
+            ))
         }
-    });
+    }
+}
+
+/// Activa el modo vigilancia sobre el directorio seleccionado.
+#[axum::debug_handler]
+async fn start_watch_handler(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let root_dir = match state.current_dir.lock().unwrap().clone() {
+        Some(dir) => dir,
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Primero debe seleccionar un directorio."})),
+            ));
+        }
+    };
 
-    Ok(StatusCode::ACCEPTED)
+    let handle = watcher::start_watch(state.clone(), root_dir.clone()).map_err(|e| {
+        error!("No se pudo iniciar la vigilancia: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("No se pudo vigilar el directorio: {}", e)})),
+        )
+    })?;
+
+    // Sustituir una vigilancia previa la detiene al soltarla.
+    *state.watcher.lock().unwrap() = Some(handle);
+    {
+        let mut status = state.status.lock().unwrap();
+        status.is_watching = true;
+        status.message = format!("[Vigilancia] Observando cambios en {}", root_dir.display());
+    }
+    Ok((StatusCode::OK, Json(json!({ "message": "Vigilancia activada." }))))
 }
 
-// MODIFICADO: Adaptado para devolver la nueva estructura RagQueryResponse.
+/// Desactiva el modo vigilancia.
+#[axum::debug_handler]
+async fn stop_watch_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let previous = state.watcher.lock().unwrap().take();
+    let mut status = state.status.lock().unwrap();
+    status.is_watching = false;
+    if let Some(handle) = previous {
+        status.message = format!("[Vigilancia] Detenida en {}", handle.root.display());
+    }
+    StatusCode::OK
+}
+
+// Devuelve la respuesta, las entidades clave y las fuentes (con páginas) usadas.
 #[axum::debug_handler]
 async fn rag_query_handler(
     State(state): State<AppState>,
     Json(payload): Json<RagQueryPayload>,
-) -> Result<Json<RagQueryResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<rag::RagAnswer>, (StatusCode, Json<serde_json::Value>)> {
+    let language_filter = match payload.language.as_deref().filter(|l| !l.trim().is_empty()) {
+        Some(requested) => Some(language::normalize(requested).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": format!("Idioma no reconocido: {}", requested)})),
+            )
+        })?),
+        None => None,
+    };
+
     let rag_result = rag::rag_query(
         &state.graph,
         &state.llm_manager,
         &state.config,
         &payload.question,
         5,
+        language_filter,
     )
     .await;
 
     match rag_result {
-        Ok((answer, key_entities)) => Ok(Json(RagQueryResponse {
-            answer,
-            key_entities,
-        })),
+        Ok(answer) => Ok(Json(answer)),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(json!({"error": format!("Error al procesar la consulta RAG: {}", e)})),
@@ -208,6 +647,27 @@ async fn rag_query_handler(
     }
 }
 
+/// Devuelve un chunk con su posición en el fichero de origen y el texto que lo rodea.
+#[axum::debug_handler]
+async fn chunk_context_handler(
+    State(state): State<AppState>,
+    AxumPath(chunk_id): AxumPath<String>,
+    Query(params): Query<ChunkContextParams>,
+) -> Result<Json<provenance::ChunkContext>, (StatusCode, Json<serde_json::Value>)> {
+    let window = params.window.unwrap_or(provenance::DEFAULT_WINDOW_CHARS);
+    match provenance::chunk_context(&state.graph, &chunk_id, window).await {
+        Ok(Some(context)) => Ok(Json(context)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": format!("No existe el chunk {}", chunk_id)})),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Error al recuperar el contexto del chunk: {}", e)})),
+        )),
+    }
+}
+
 #[axum::debug_handler]
 async fn status_handler(State(state): State<AppState>) -> Json<Status> {
     Json(state.status.lock().unwrap().clone())
@@ -1,16 +1,22 @@
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use neo4rs::Graph;
-use tokio::sync::oneshot;
-use crate::{config::AppConfig, llm::LlmManager};
+use tokio::sync::{oneshot, Semaphore};
+use crate::{config::AppConfig, jobs::JobManager, llm::LlmManager, watcher::WatchHandle};
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: AppConfig,
     pub graph: Arc<Graph>,
     pub llm_manager: LlmManager,
+    /// Peticiones al LLM en vuelo entre todas las ingestas (`INGEST_MAX_LLM_REQUESTS`).
+    pub llm_permits: Arc<Semaphore>,
     pub status: Arc<Mutex<Status>>,
     pub current_dir: Arc<Mutex<Option<PathBuf>>>,
+    /// Vigilancia activa del sistema de archivos, si la hay.
+    pub watcher: Arc<Mutex<Option<WatchHandle>>>,
+    /// Trabajos de ingesta en curso y su historial.
+    pub jobs: JobManager,
     pub shutdown_sender: Arc<Mutex<Option<oneshot::Sender<()>>>>,
 }
 
@@ -20,4 +26,5 @@ pub struct Status {
     pub is_busy: bool,
     pub message: String,
     pub progress: f32, // Valor entre 0.0 y 1.0
+    pub is_watching: bool,
 }
\ No newline at end of file
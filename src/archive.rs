@@ -0,0 +1,246 @@
+//! Archivos comprimidos (`.zip`, `.tar.gz`, `.tgz`, `.tar`) tratados como
+//! directorios virtuales: sus miembros se extraen en memoria y se ingieren
+//! con rutas del tipo `docs/paquete.zip!/manual/guia.md`.
+
+use std::{
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+/// Separa la ruta del archivo de la del miembro en las rutas virtuales.
+pub const SEPARATOR: &str = "!/";
+
+/// Un fichero contenido en un archivo comprimido.
+#[derive(Debug)]
+pub struct ArchiveMember {
+    /// Ruta dentro del archivo, con `/` como separador.
+    pub inner_path: String,
+    pub bytes: Vec<u8>,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn format_of(name: &str) -> Option<Format> {
+    let name = name.to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else {
+        None
+    }
+}
+
+/// Si la ruta es un archivo comprimido soportado.
+pub fn is_archive(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| format_of(&name.to_string_lossy()).is_some())
+}
+
+/// Ruta virtual de un miembro: `<archivo>!/<ruta interna>`.
+pub fn virtual_path(archive: &str, inner_path: &str) -> String {
+    format!("{archive}{SEPARATOR}{inner_path}")
+}
+
+/// Ruta en disco de la que procede una ruta (la del archivo si es virtual).
+pub fn outer_path(path: &str) -> &Path {
+    Path::new(path.split_once(SEPARATOR).map_or(path, |(archive, _)| archive))
+}
+
+/// Lee los miembros de un archivo. `accept(ruta interna, tamaño)` decide qué
+/// miembros se extraen; con `max_member_bytes > 0` los que descomprimidos
+/// superen ese tamaño se descartan sin llegar a cargarse enteros. Con
+/// `max_total_bytes > 0` el archivo se rechaza en cuanto los miembros
+/// extraídos suman más (una bomba de descompresión no agota la memoria).
+/// Devuelve también cuántos miembros se descartaron.
+pub fn read_members(
+    name: &str,
+    bytes: &[u8],
+    max_member_bytes: u64,
+    max_total_bytes: u64,
+    mut accept: impl FnMut(&str, u64) -> bool,
+) -> Result<(Vec<ArchiveMember>, u32)> {
+    let format = format_of(name).ok_or_else(|| anyhow!("Formato de archivo no soportado: {name}"))?;
+    let mut members = Vec::new();
+    let mut rejected = 0;
+    let mut budget = Budget::new(max_member_bytes, max_total_bytes);
+
+    match format {
+        Format::Zip => {
+            let mut zip = ZipArchive::new(Cursor::new(bytes))?;
+            for i in 0..zip.len() {
+                let file = zip.by_index(i)?;
+                // `enclosed_name` descarta rutas absolutas o con `..`.
+                let Some(inner) = file.enclosed_name() else { continue };
+                if file.is_dir() {
+                    continue;
+                }
+                let inner_path = inner.to_string_lossy().replace('\\', "/");
+                if !accept(&inner_path, file.size()) {
+                    rejected += 1;
+                    continue;
+                }
+                let modified = file.last_modified().and_then(|dt| {
+                    NaiveDate::from_ymd_opt(dt.year().into(), dt.month().into(), dt.day().into())?
+                        .and_hms_opt(dt.hour().into(), dt.minute().into(), dt.second().into())
+                        .map(|naive| Utc.from_utc_datetime(&naive))
+                });
+                match budget.read(file, name)? {
+                    Some(bytes) => members.push(ArchiveMember { inner_path, bytes, modified }),
+                    None => rejected += 1,
+                }
+            }
+        }
+        Format::Tar | Format::TarGz => {
+            let reader: Box<dyn Read + '_> = match format {
+                Format::TarGz => Box::new(GzDecoder::new(bytes)),
+                _ => Box::new(bytes),
+            };
+            let mut tar = tar::Archive::new(reader);
+            for entry in tar.entries()? {
+                let entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let inner = entry.path()?.into_owned();
+                if inner.is_absolute() || inner.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+                    continue;
+                }
+                let inner_path = inner.to_string_lossy().trim_start_matches("./").replace('\\', "/");
+                if !accept(&inner_path, entry.size()) {
+                    rejected += 1;
+                    continue;
+                }
+                let modified = entry
+                    .header()
+                    .mtime()
+                    .ok()
+                    .and_then(|secs| DateTime::<Utc>::from_timestamp(secs as i64, 0));
+                match budget.read(entry, name)? {
+                    Some(bytes) => members.push(ArchiveMember { inner_path, bytes, modified }),
+                    None => rejected += 1,
+                }
+            }
+        }
+    }
+    Ok((members, rejected))
+}
+
+/// Lee el contenido de un miembro a partir de su ruta virtual.
+pub fn read_member(path: &str) -> Result<Vec<u8>> {
+    let (archive, inner_path) =
+        path.split_once(SEPARATOR).ok_or_else(|| anyhow!("No es la ruta de un miembro de archivo: {path}"))?;
+    let bytes = std::fs::read(archive)?;
+    let (mut members, _) = read_members(archive, &bytes, 0, 0, |candidate, _| candidate == inner_path)?;
+    members.pop().map(|m| m.bytes).ok_or_else(|| anyhow!("{inner_path} ya no está en {archive}"))
+}
+
+/// Límites de lo que se carga en memoria de un archivo (0 = sin límite).
+/// También los usa `extract` para las entradas de DOCX, ODT y EPUB.
+pub struct Budget {
+    max_member_bytes: u64,
+    max_total_bytes: u64,
+    loaded: u64,
+}
+
+impl Budget {
+    pub fn new(max_member_bytes: u64, max_total_bytes: u64) -> Self {
+        Self { max_member_bytes, max_total_bytes, loaded: 0 }
+    }
+
+    /// Lee un miembro sin pasar de los límites: el tamaño declarado en la
+    /// cabecera no es de fiar. `None` si el miembro supera el máximo por
+    /// miembro; error si el archivo supera el total.
+    pub fn read(&mut self, reader: impl Read, archive: &str) -> Result<Option<Vec<u8>>> {
+        let member_limit = if self.max_member_bytes == 0 { u64::MAX } else { self.max_member_bytes };
+        let total_left = if self.max_total_bytes == 0 { u64::MAX } else { self.max_total_bytes - self.loaded };
+        let limit = member_limit.min(total_left);
+
+        let mut bytes = Vec::new();
+        reader.take(limit.saturating_add(1)).read_to_end(&mut bytes)?;
+        let len = bytes.len() as u64;
+        if len > member_limit {
+            return Ok(None);
+        }
+        if len > total_left {
+            return Err(anyhow!(
+                "{archive} supera los {} bytes descomprimidos permitidos por archivo",
+                self.max_total_bytes
+            ));
+        }
+        self.loaded += len;
+        Ok(Some(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn zip(files: &[(&str, usize)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, size) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&vec![b'a'; *size]).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn paths(members: &[ArchiveMember]) -> Vec<&str> {
+        members.iter().map(|m| m.inner_path.as_str()).collect()
+    }
+
+    #[test]
+    fn members_over_their_limit_are_dropped() {
+        let bytes = zip(&[("docs/a.md", 10), ("grande.md", 4096), ("../fuera.md", 10), ("c.md", 10)]);
+
+        let (members, rejected) = read_members("paquete.zip", &bytes, 1024, 0, |_, _| true).unwrap();
+        assert_eq!(paths(&members), ["docs/a.md", "c.md"]);
+        assert_eq!(rejected, 1);
+        assert_eq!(members[0].bytes.len(), 10);
+
+        let (members, rejected) = read_members("paquete.zip", &bytes, 0, 0, |path, _| path != "c.md").unwrap();
+        assert_eq!(paths(&members), ["docs/a.md", "grande.md"]);
+        assert_eq!(rejected, 1);
+    }
+
+    #[test]
+    fn archives_over_the_total_budget_are_rejected() {
+        let bytes = zip(&[("a.md", 600), ("b.md", 600)]);
+        assert!(read_members("paquete.zip", &bytes, 0, 1000, |_, _| true).is_err());
+        // Los miembros descartados no cuentan para el total.
+        let (members, _) = read_members("paquete.zip", &bytes, 0, 1000, |path, _| path == "b.md").unwrap();
+        assert_eq!(paths(&members), ["b.md"]);
+
+        let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for name in ["a.md", "b.md"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(600);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, &[b'a'; 600][..]).unwrap();
+        }
+        let bytes = tar.into_inner().unwrap().finish().unwrap();
+        // Mucho menos comprimido que descomprimido: el límite es sobre lo descomprimido.
+        assert!(bytes.len() < 1000);
+        assert!(read_members("paquete.tar.gz", &bytes, 0, 1000, |_, _| true).is_err());
+        assert_eq!(read_members("paquete.tar.gz", &bytes, 0, 0, |_, _| true).unwrap().0.len(), 2);
+    }
+}
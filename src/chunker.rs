@@ -0,0 +1,645 @@
+//! División de texto en chunks medidos en tokens, con solapamiento.
+//!
+//! Se parte por párrafos; los párrafos que no caben se dividen por frases y,
+//! en último caso, por tokens. Los trozos resultantes se empaquetan hasta el
+//! tamaño configurado, repitiendo al inicio de cada chunk las últimas unidades
+//! del anterior hasta cubrir el solapamiento pedido.
+//!
+//! Markdown y código fuente tienen estrategias propias que respetan su
+//! estructura (encabezados y elementos de primer nivel, respectivamente).
+
+use std::ops::Range;
+
+use tiktoken_rs::{cl100k_base_singleton, CoreBPE};
+
+/// Tamaño y solapamiento de los chunks, en tokens.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkingConfig {
+    pub chunk_size_tokens: usize,
+    pub chunk_overlap_tokens: usize,
+}
+
+/// Un chunk de texto junto con su número de tokens.
+#[derive(Debug, Clone)]
+pub struct TextChunk {
+    pub text: String,
+    pub tokens: usize,
+    /// Ruta de encabezados (Markdown) o elemento de código al que pertenece.
+    pub section: Option<String>,
+    /// Rango de bytes del texto de origen que cubre el chunk. Vacío si el
+    /// chunk no corresponde a un tramo del texto (registros estructurados).
+    pub span: Range<usize>,
+}
+
+/// Unidad mínima de empaquetado: un párrafo, una frase o un trozo de tokens.
+struct Unit {
+    text: String,
+    tokens: usize,
+    span: Range<usize>,
+    /// Separador a usar delante de esta unidad si no abre el chunk.
+    joiner: &'static str,
+}
+
+/// Tokenizador usado por los modelos de embeddings de OpenAI (cl100k_base).
+fn tokenizer() -> &'static CoreBPE {
+    cl100k_base_singleton()
+}
+
+/// Cuenta los tokens de un texto.
+pub fn count_tokens(text: &str) -> usize {
+    tokenizer().encode_ordinary(text).len()
+}
+
+/// Divide `text` en chunks de como máximo `chunk_size_tokens` tokens.
+pub fn split_into_chunks(text: &str, cfg: &ChunkingConfig) -> Vec<TextChunk> {
+    let max_tokens = cfg.chunk_size_tokens.max(1);
+    let units = split_into_units(text, max_tokens);
+    pack_units(units, max_tokens, cfg.chunk_overlap_tokens.min(max_tokens / 2))
+}
+
+/// Posición en bytes de `part` dentro de `whole`, del que debe ser un subslice.
+fn offset_in(whole: &str, part: &str) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+fn split_into_units(text: &str, max_tokens: usize) -> Vec<Unit> {
+    let mut units = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() {
+            continue;
+        }
+        let start = offset_in(text, paragraph);
+        let tokens = count_tokens(paragraph);
+        if tokens <= max_tokens {
+            units.push(Unit {
+                text: paragraph.to_string(),
+                tokens,
+                span: start..start + paragraph.len(),
+                joiner: "\n\n",
+            });
+            continue;
+        }
+
+        // Párrafo demasiado largo: frase a frase.
+        for (i, sentence) in split_sentences(paragraph).into_iter().enumerate() {
+            let joiner = if i == 0 { "\n\n" } else { " " };
+            let start = offset_in(text, sentence);
+            let tokens = count_tokens(sentence);
+            if tokens <= max_tokens {
+                units.push(Unit { text: sentence.to_string(), tokens, span: start..start + sentence.len(), joiner });
+            } else {
+                // Frase demasiado larga: cortes duros por tokens. Los trozos
+                // concatenados reproducen la frase, así que sus posiciones se acumulan.
+                let mut piece_start = start;
+                for (j, piece) in split_by_tokens(sentence, max_tokens).into_iter().enumerate() {
+                    let tokens = count_tokens(&piece);
+                    let span = piece_start..piece_start + piece.len();
+                    piece_start = span.end;
+                    units.push(Unit { text: piece, tokens, span, joiner: if j == 0 { joiner } else { "" } });
+                }
+            }
+        }
+    }
+    units
+}
+
+/// Separa un párrafo en frases, cortando tras `.`, `!`, `?` o saltos de línea.
+fn split_sentences(paragraph: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = paragraph.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let is_boundary = matches!(c, '.' | '!' | '?' | '\n')
+            && chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if is_boundary {
+            let end = i + c.len_utf8();
+            let sentence = paragraph[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let rest = paragraph[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+fn split_by_tokens(text: &str, max_tokens: usize) -> Vec<String> {
+    let bpe = tokenizer();
+    let tokens = bpe.encode_ordinary(text);
+    let mut pieces: Vec<String> = Vec::new();
+    let mut start = 0;
+
+    while start < tokens.len() {
+        // Un corte puede caer en mitad de un carácter multibyte, y el trozo
+        // decodificado puede tokenizarse distinto por sí solo: se busca la
+        // ventana más larga que decodifique y siga cabiendo en `max_tokens`.
+        let limit = (start + max_tokens).min(tokens.len());
+        let fitting = (start + 1..=limit).rev().find_map(|end| {
+            let piece = bpe.decode(tokens[start..end].to_vec()).ok()?;
+            (count_tokens(&piece) <= max_tokens).then_some((end, piece))
+        });
+        // Si ninguna cabe, se amplía la ventana hasta que decodifique.
+        let (end, piece) = fitting.unwrap_or_else(|| {
+            let mut end = limit;
+            loop {
+                match bpe.decode(tokens[start..end].to_vec()) {
+                    Ok(piece) => break (end, piece),
+                    Err(_) if end < tokens.len() => end += 1,
+                    // El texto de entrada es UTF-8 válido, así que el resto siempre decodifica.
+                    Err(_) => break (end, String::new()),
+                }
+            }
+        });
+        pieces.push(piece);
+        start = end;
+    }
+    pieces
+}
+
+fn pack_units(units: Vec<Unit>, max_tokens: usize, overlap_tokens: usize) -> Vec<TextChunk> {
+    let mut chunks = Vec::new();
+    let mut current: Vec<&Unit> = Vec::new();
+    let mut current_tokens = 0;
+
+    for unit in &units {
+        if current_tokens + unit.tokens > max_tokens && !current.is_empty() {
+            chunks.push(render_chunk(&current));
+
+            // Arrastrar las últimas unidades que quepan en el solapamiento.
+            let mut carried: Vec<&Unit> = Vec::new();
+            let mut carried_tokens = 0;
+            for previous in current.iter().rev() {
+                if carried_tokens + previous.tokens > overlap_tokens
+                    || carried_tokens + previous.tokens + unit.tokens > max_tokens
+                {
+                    break;
+                }
+                carried_tokens += previous.tokens;
+                carried.push(previous);
+            }
+            carried.reverse();
+            current = carried;
+            current_tokens = carried_tokens;
+        }
+        current.push(unit);
+        current_tokens += unit.tokens;
+    }
+    // El último chunk siempre termina en una unidad nueva, no sólo solapamiento.
+    if !current.is_empty() {
+        chunks.push(render_chunk(&current));
+    }
+    chunks
+}
+
+fn render_chunk(units: &[&Unit]) -> TextChunk {
+    let mut text = String::new();
+    for (i, unit) in units.iter().enumerate() {
+        if i > 0 {
+            text.push_str(unit.joiner);
+        }
+        text.push_str(&unit.text);
+    }
+    let tokens = count_tokens(&text);
+    let span = units.first().map_or(0, |u| u.span.start)..units.last().map_or(0, |u| u.span.end);
+    TextChunk { text, tokens, section: None, span }
+}
+
+// ---------------------------------------------------------------------------
+// ESTRATEGIAS SEGÚN EL FORMATO
+// ---------------------------------------------------------------------------
+
+/// Elige la estrategia de división según la extensión del fichero.
+pub fn split_for_extension(text: &str, extension: &str, cfg: &ChunkingConfig) -> Vec<TextChunk> {
+    match extension {
+        // El extractor de HTML emite los encabezados con sintaxis Markdown.
+        "md" | "markdown" | "html" | "htm" => split_markdown(text, cfg),
+        "rs" | "js" | "css" => split_source_code(text, extension, cfg),
+        _ => split_into_chunks(text, cfg),
+    }
+}
+
+/// Divide un Markdown siguiendo su jerarquía de encabezados. Cada sección se
+/// trocea por separado y sus chunks llevan la ruta de encabezados
+/// (p. ej. `Instalación > Requisitos`).
+pub fn split_markdown(text: &str, cfg: &ChunkingConfig) -> Vec<TextChunk> {
+    let mut chunks = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut section_path: Option<String> = None;
+    // Cada sección es un tramo contiguo del texto: basta con recordar dónde empieza.
+    let mut section_start = 0;
+    let mut in_fence = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let heading = if in_fence { None } else { parse_heading(line) };
+
+        if let Some((level, title)) = heading {
+            let line_start = offset_in(text, line);
+            push_section(&mut chunks, text, section_start..line_start, section_path.take(), cfg);
+            section_start = line_start;
+
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, title));
+            section_path = Some(
+                headings.iter().map(|(_, t)| t.as_str()).collect::<Vec<_>>().join(" > "),
+            );
+        }
+    }
+    push_section(&mut chunks, text, section_start..text.len(), section_path, cfg);
+    chunks
+}
+
+/// Reconoce un encabezado ATX (`#` a `######`) y devuelve nivel y título.
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim();
+    (!title.is_empty()).then(|| (level, title.to_string()))
+}
+
+/// Trocea el tramo `range` de `text` y etiqueta sus chunks con `section`.
+fn push_section(
+    chunks: &mut Vec<TextChunk>,
+    text: &str,
+    range: Range<usize>,
+    section: Option<String>,
+    cfg: &ChunkingConfig,
+) {
+    let base = range.start;
+    for mut chunk in split_into_chunks(&text[range], cfg) {
+        chunk.section = section.clone();
+        chunk.span = chunk.span.start + base..chunk.span.end + base;
+        chunks.push(chunk);
+    }
+}
+
+/// Divide código fuente por elementos de primer nivel (funciones, `impl`,
+/// módulos, clases...). Los elementos pequeños consecutivos se agrupan en un
+/// mismo chunk. Los contenedores (`impl`, `mod`, clases...) que no caben se
+/// abren para agrupar sus elementos internos del mismo modo; el resto de
+/// elementos que no caben se trocean conservando su nombre.
+pub fn split_source_code(text: &str, extension: &str, cfg: &ChunkingConfig) -> Vec<TextChunk> {
+    let rust = extension == "rs";
+    let mut chunks = Vec::new();
+    push_items(&mut chunks, text, split_top_level_items(text, rust), rust, cfg);
+    chunks
+}
+
+/// Agrupa `items` (rangos de `text`) en chunks de como máximo `chunk_size_tokens`.
+fn push_items(
+    chunks: &mut Vec<TextChunk>,
+    text: &str,
+    items: Vec<(String, Range<usize>)>,
+    rust: bool,
+    cfg: &ChunkingConfig,
+) {
+    let max_tokens = cfg.chunk_size_tokens.max(1);
+    let mut group: Vec<(String, Range<usize>)> = Vec::new();
+    let mut group_tokens = 0;
+
+    for (name, item) in items {
+        let tokens = count_tokens(&text[item.clone()]);
+        if tokens > max_tokens {
+            flush_group(chunks, text, &mut group);
+            group_tokens = 0;
+            match inner_items(text, &name, item.clone(), rust) {
+                Some(inner) => push_items(chunks, text, inner, rust, cfg),
+                None => push_section(chunks, text, item, Some(name), cfg),
+            }
+            continue;
+        }
+        if group_tokens + tokens > max_tokens {
+            flush_group(chunks, text, &mut group);
+            group_tokens = 0;
+        }
+        group_tokens += tokens;
+        group.push((name, item));
+    }
+    flush_group(chunks, text, &mut group);
+}
+
+/// Abre un nivel de llaves de un contenedor (`impl`, `mod`, `trait`, clase...):
+/// devuelve su cabecera, sus elementos internos (con nombres como
+/// `impl Foo > fn bar`) y la llave de cierre. `None` si no es un contenedor
+/// o su cuerpo no tiene elementos, y entonces se trocea como texto.
+fn inner_items(text: &str, name: &str, item: Range<usize>, rust: bool) -> Option<Vec<(String, Range<usize>)>> {
+    let container = name.split_whitespace().next()?;
+    if !matches!(container, "impl" | "mod" | "trait" | "class" | "interface") {
+        return None;
+    }
+
+    // La cabecera llega hasta la línea que abre la primera llave.
+    let source = &text[item.clone()];
+    let mut scan = ScanState::default();
+    let header_end = source.lines().find_map(|line| {
+        (brace_delta(line, rust, &mut scan) > 0).then(|| offset_in(source, line) + line.len())
+    })?;
+    // El cuerpo termina al empezar la línea de la llave de cierre.
+    let closing = source.rfind('}')?;
+    let body_end = source[..closing].rfind('\n').map_or(0, |newline| newline + 1);
+    if body_end <= header_end {
+        return None;
+    }
+
+    let base = item.start + header_end;
+    let inner = split_top_level_items(&source[header_end..body_end], rust);
+    if inner.is_empty() {
+        return None;
+    }
+    let mut items = vec![(name.to_string(), item.start..base)];
+    items.extend(
+        inner
+            .into_iter()
+            .map(|(inner_name, range)| (format!("{} > {}", name, inner_name), range.start + base..range.end + base)),
+    );
+    items.push((name.to_string(), item.start + body_end..item.end));
+    Some(items)
+}
+
+fn flush_group(chunks: &mut Vec<TextChunk>, source: &str, group: &mut Vec<(String, Range<usize>)>) {
+    let (Some((_, first)), Some((_, last))) = (group.first(), group.last()) else { return };
+    let span = first.start..last.end;
+    let text = group.iter().map(|(_, item)| source[item.clone()].trim()).collect::<Vec<_>>().join("\n\n");
+    let mut names: Vec<&str> = Vec::new();
+    for (name, _) in group.iter() {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+    let tokens = count_tokens(&text);
+    chunks.push(TextChunk { text, tokens, section: Some(names.join(", ")), span });
+    group.clear();
+}
+
+/// Separa el código en elementos de primer nivel siguiendo la profundidad de
+/// llaves. Comentarios y atributos previos se adjuntan al elemento siguiente.
+/// Devuelve el nombre de cada elemento y su rango de bytes en `text`.
+fn split_top_level_items(text: &str, rust: bool) -> Vec<(String, Range<usize>)> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut name: Option<String> = None;
+    let mut depth: i64 = 0;
+    let mut scan = ScanState::default();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let is_preamble = trimmed.is_empty()
+            || trimmed.starts_with("//")
+            || trimmed.starts_with("/*")
+            || trimmed.starts_with('*')
+            || trimmed.starts_with("#[")
+            || trimmed.starts_with("#![")
+            || trimmed.starts_with('@');
+
+        if depth == 0 && name.is_none() && !is_preamble && !scan.in_block_comment {
+            name = Some(item_name(trimmed));
+        }
+        let line_end = offset_in(text, line) + line.len();
+
+        depth += brace_delta(line, rust, &mut scan);
+        depth = depth.max(0);
+
+        // Un elemento termina al volver a profundidad 0 con `}` o `;`.
+        if depth == 0 && scan.in_string.is_none() && name.is_some() && (trimmed.ends_with('}') || trimmed.ends_with("};") || trimmed.ends_with(';')) {
+            items.push((name.take().unwrap_or_default(), trim_range(text, start..line_end)));
+            start = line_end;
+        }
+    }
+    if !text[start..].trim().is_empty() {
+        items.push((name.unwrap_or_else(|| "(resto)".to_string()), trim_range(text, start..text.len())));
+    }
+    items
+}
+
+/// Recorta los espacios en blanco de los extremos de un rango de `text`.
+fn trim_range(text: &str, range: Range<usize>) -> Range<usize> {
+    let slice = &text[range.clone()];
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    let end = range.end - (slice.len() - slice.trim_end().len());
+    start..end.max(start)
+}
+
+/// Estado del escáner que se arrastra entre líneas (cadenas y comentarios multilínea).
+#[derive(Default)]
+struct ScanState {
+    in_block_comment: bool,
+    in_string: Option<char>,
+}
+
+/// Variación de la profundidad de llaves en una línea, ignorando cadenas y
+/// comentarios. En Rust la comilla simple abre un carácter o un lifetime; en
+/// el resto de lenguajes abre una cadena.
+fn brace_delta(line: &str, rust: bool, scan: &mut ScanState) -> i64 {
+    let mut delta = 0;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if scan.in_block_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                scan.in_block_comment = false;
+            }
+            continue;
+        }
+        if let Some(quote) = scan.in_string {
+            if c == '\\' {
+                chars.next();
+            } else if c == quote {
+                scan.in_string = None;
+            }
+            continue;
+        }
+        match c {
+            '/' if chars.peek() == Some(&'/') => break,
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                scan.in_block_comment = true;
+            }
+            '"' | '`' => scan.in_string = Some(c),
+            '\'' if !rust => scan.in_string = Some(c),
+            '\'' => {
+                let lookahead: Vec<char> = chars.clone().take(3).collect();
+                match lookahead.as_slice() {
+                    // 'x'
+                    [_, '\'', ..] => {
+                        chars.nth(1);
+                    }
+                    // '\n'
+                    ['\\', _, '\'', ..] => {
+                        chars.nth(2);
+                    }
+                    // Lifetime ('a): no abre nada.
+                    _ => {}
+                }
+            }
+            '{' => delta += 1,
+            '}' => delta -= 1,
+            _ => {}
+        }
+    }
+    delta
+}
+
+/// Nombre legible de un elemento a partir de su primera línea
+/// (`fn ingest_file`, `impl Display for IngestionSummary`, `class Foo`...).
+fn item_name(signature: &str) -> String {
+    const KEYWORDS: [&str; 14] = [
+        "fn", "struct", "enum", "trait", "mod", "impl", "type", "const", "static", "union",
+        "macro_rules!", "function", "class", "interface",
+    ];
+    const MODIFIERS: [&str; 9] = ["pub", "async", "unsafe", "extern", "export", "default", "let", "var", "\"C\""];
+
+    let head = signature.split(['{', '(', '=']).next().unwrap_or(signature).trim();
+    let words: Vec<&str> = head.split_whitespace().collect();
+
+    let start = words
+        .iter()
+        .position(|w| !MODIFIERS.contains(w) && !w.starts_with("pub("))
+        .unwrap_or(0);
+    let relevant = &words[start..];
+
+    let name = match relevant.first() {
+        // Las importaciones se agrupan bajo un único nombre.
+        Some(&keyword @ ("use" | "import")) => keyword.to_string(),
+        Some(keyword) if KEYWORDS.contains(keyword) => relevant.join(" "),
+        _ => head.to_string(),
+    };
+    name.chars().take(120).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(chunk_size_tokens: usize, chunk_overlap_tokens: usize) -> ChunkingConfig {
+        ChunkingConfig { chunk_size_tokens, chunk_overlap_tokens }
+    }
+
+    fn paragraphs(count: usize) -> String {
+        (0..count)
+            .map(|i| format!("Párrafo número {i} del documento de prueba con algunas palabras más."))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    #[test]
+    fn chunks_stay_within_the_token_limit_and_point_at_their_source() {
+        let text = paragraphs(40);
+        let chunks = split_into_chunks(&text, &config(60, 0));
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.tokens <= 60, "{} tokens", chunk.tokens);
+            assert_eq!(chunk.tokens, count_tokens(&chunk.text));
+            // Los párrafos se unen con el mismo separador que en el original.
+            assert_eq!(&text[chunk.span.clone()], chunk.text);
+        }
+        assert_eq!(chunks.first().unwrap().span.start, 0);
+        assert_eq!(chunks.last().unwrap().span.end, text.len());
+    }
+
+    #[test]
+    fn consecutive_chunks_repeat_the_overlap() {
+        let text = paragraphs(40);
+        let chunks = split_into_chunks(&text, &config(60, 20));
+
+        for pair in chunks.windows(2) {
+            let first_paragraph = pair[1].text.split("\n\n").next().unwrap();
+            assert!(pair[0].text.ends_with(first_paragraph), "{:?} / {:?}", pair[0].text, pair[1].text);
+            assert!(pair[1].span.start < pair[0].span.end);
+        }
+    }
+
+    #[test]
+    fn overlap_is_capped_so_chunks_always_advance() {
+        let text = paragraphs(40);
+        // Un solapamiento mayor que el chunk se limita a la mitad del tamaño.
+        let chunks = split_into_chunks(&text, &config(60, 500));
+
+        assert!(chunks.len() > 1);
+        for pair in chunks.windows(2) {
+            assert!(pair[1].span.end > pair[0].span.end);
+            assert!(pair[1].tokens <= 60);
+        }
+        assert_eq!(chunks.last().unwrap().span.end, text.len());
+    }
+
+    #[test]
+    fn oversized_sentences_are_cut_by_tokens_without_breaking_characters() {
+        let text = "ñandú ".repeat(400);
+        let chunks = split_into_chunks(&text, &config(50, 0));
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.tokens <= 50, "{} tokens", chunk.tokens);
+            assert!(!chunk.text.contains('\u{FFFD}'));
+        }
+        let rebuilt: String = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(rebuilt, text.trim());
+    }
+
+    #[test]
+    fn markdown_chunks_carry_their_heading_path() {
+        let text = "Intro sin encabezado.\n\n# Instalación\n\nPasos generales.\n\n## Requisitos\n\nRust y Neo4j.\n\n```sh\n# no es un encabezado\ncargo run\n```\n\n# Uso\n\nArranca el servidor.\n";
+        let chunks = split_markdown(text, &config(300, 0));
+        let sections: Vec<Option<&str>> = chunks.iter().map(|c| c.section.as_deref()).collect();
+
+        assert_eq!(
+            sections,
+            [None, Some("Instalación"), Some("Instalación > Requisitos"), Some("Uso")]
+        );
+        assert!(chunks[2].text.contains("# no es un encabezado"));
+        for chunk in &chunks {
+            assert_eq!(&text[chunk.span.clone()], chunk.text);
+        }
+    }
+
+    #[test]
+    fn small_source_items_are_grouped_by_name() {
+        let text = "use std::fmt;\n\n/// Suma.\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nstruct Point {\n    x: i32,\n}\n";
+        let chunks = split_source_code(text, "rs", &config(300, 0));
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].section.as_deref(), Some("use, fn add, struct Point"));
+        assert!(chunks[0].text.starts_with("use std::fmt;"));
+        assert!(chunks[0].text.contains("/// Suma.\nfn add"));
+    }
+
+    #[test]
+    fn oversized_containers_are_split_by_their_inner_items() {
+        let methods: String = (0..12)
+            .map(|i| {
+                format!(
+                    "    /// Método {i}.\n    fn method_{i}(&self) -> usize {{\n        let text = \"{{ llave en cadena\";\n        text.len() + {i}\n    }}\n\n"
+                )
+            })
+            .collect();
+        let text = format!("impl Foo {{\n{methods}}}\n\nfn after() {{}}\n");
+        let chunks = split_source_code(&text, "rs", &config(80, 0));
+
+        assert!(chunks.len() > 2);
+        // Ningún método queda partido entre dos chunks.
+        for i in 0..12 {
+            let signature = format!("fn method_{i}(&self)");
+            let holder = chunks.iter().find(|c| c.text.contains(&signature)).unwrap();
+            assert!(holder.text.contains(&format!("text.len() + {i}\n    }}")), "{}", holder.text);
+            assert!(holder.section.as_deref().unwrap().contains(&format!("impl Foo > fn method_{i}")));
+        }
+        assert!(chunks.iter().any(|c| c.section.as_deref() == Some("fn after")));
+    }
+}
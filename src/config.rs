@@ -1,13 +1,15 @@
 //! Carga y gestión de configuración de la aplicación (Neo4j + LLM).
 
-use std::env;
+use std::{env, str::FromStr};
 use anyhow::{anyhow, Result};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LlmProvider {
     OpenAI,
     Gemini,
     Ollama,
+    /// Cualquier servidor que hable la API de OpenAI (vLLM, llama.cpp...).
+    OpenAICompatible,
 }
 
 impl LlmProvider {
@@ -16,9 +18,82 @@ impl LlmProvider {
             "openai" => Ok(Self::OpenAI),
             "gemini" => Ok(Self::Gemini),
             "ollama" => Ok(Self::Ollama),
+            "openai_compatible" | "openai-compatible" => Ok(Self::OpenAICompatible),
             other => Err(anyhow!("Proveedor LLM no soportado: {other}")),
         }
     }
+
+    /// Modelo de embeddings si no se configura otro. Los servidores
+    /// compatibles con OpenAI no tienen uno por defecto.
+    pub fn default_embedding_model(&self) -> Option<&'static str> {
+        match self {
+            Self::OpenAI => Some("text-embedding-3-small"),
+            Self::Gemini => Some("text-embedding-004"),
+            Self::Ollama => Some("nomic-embed-text"),
+            Self::OpenAICompatible => None,
+        }
+    }
+
+    /// Dimensiones de los vectores del modelo de embeddings por defecto.
+    pub fn default_embedding_dimensions(&self) -> Option<usize> {
+        match self {
+            Self::OpenAI => Some(1536),
+            Self::Gemini | Self::Ollama => Some(768),
+            Self::OpenAICompatible => None,
+        }
+    }
+
+    /// Modelo de chat (respuestas y extracción) si no se configura otro.
+    pub fn default_chat_model(&self) -> Option<&'static str> {
+        match self {
+            Self::OpenAI => Some("gpt-4o-mini"),
+            Self::Gemini => Some("gemini-2.0-flash"),
+            Self::Ollama => Some("llama3.2"),
+            Self::OpenAICompatible => None,
+        }
+    }
+}
+
+/// Qué hacer con un fichero en el que se detectan secretos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretPolicy {
+    /// No se ingiere el fichero.
+    Skip,
+    /// Se ingiere sustituyendo cada secreto por un marcador.
+    Redact,
+    /// No se analiza.
+    Off,
+}
+
+impl SecretPolicy {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "redact" => Ok(Self::Redact),
+            "off" => Ok(Self::Off),
+            other => Err(anyhow!("Política de secretos no soportada: {other} (skip, redact u off)")),
+        }
+    }
+}
+
+/// Tipo de dato personal que se redacta antes de enviar texto al proveedor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    /// DNI/NIE españoles y SSN estadounidenses.
+    NationalId,
+}
+
+impl PiiKind {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "email" => Ok(Self::Email),
+            "phone" => Ok(Self::Phone),
+            "national_id" => Ok(Self::NationalId),
+            other => Err(anyhow!("Tipo de dato personal no soportado: {other} (email, phone o national_id)")),
+        }
+    }
 }
 
 /// Configuración completa de la aplicación.
@@ -29,12 +104,84 @@ pub struct AppConfig {
     pub neo4j_password: String,
     pub server_addr: String,
 
-    pub llm_provider: LlmProvider,
+    /// Proveedor de cada uso del LLM; por defecto todos el de `LLM_PROVIDER`.
+    pub embedding_provider: LlmProvider,
+    pub answer_provider: LlmProvider,
+    pub extraction_provider: LlmProvider,
     pub llm_embedding_model: String,
-    pub llm_chat_model: String,
+    /// Dimensiones de los embeddings, para crear el índice vectorial.
+    pub llm_embedding_dimensions: usize,
+    /// Modelo que redacta las respuestas RAG.
+    pub llm_answer_model: String,
+    /// Modelo que extrae entidades y relaciones durante la ingesta.
+    pub llm_extraction_model: String,
+    /// URL de la API de Ollama (o de un servidor compatible).
+    pub ollama_base_url: String,
+    /// URL base (con `/v1`) de un servidor compatible con la API de OpenAI.
+    pub openai_compatible_base_url: String,
+    /// Clave de ese servidor; vacía si no la pide.
+    pub openai_compatible_api_key: String,
+    /// Usa el backend simulado en lugar de los proveedores (`--offline`).
+    pub llm_offline: bool,
+    /// Guion JSON con respuestas y extracciones del backend simulado.
+    pub llm_offline_script: String,
+
+    /// Ficheros que se procesan en paralelo durante la ingesta.
+    pub ingest_max_concurrent_files: usize,
+    /// Peticiones de extracción en vuelo por fichero.
+    pub ingest_max_llm_requests_per_file: usize,
+    /// Peticiones al LLM en vuelo en total, sumando todos los ficheros.
+    pub ingest_max_llm_requests: usize,
+
+    /// Patrones (relativos a la raíz) de los ficheros a ingerir; vacío = todos.
+    pub ingest_include: Vec<String>,
+    /// Patrones de ficheros y directorios a descartar.
+    pub ingest_exclude: Vec<String>,
+    /// Tamaño máximo de fichero en bytes (0 = sin límite).
+    pub ingest_max_file_size_bytes: u64,
+    /// Bytes descomprimidos que se cargan, en total, de un archivo comprimido, y
+    /// tamaño máximo del propio archivo (0 = sin límite).
+    pub ingest_max_archive_bytes: u64,
+    /// Respetar los `.gitignore`/`.ignore` del directorio ingerido.
+    pub ingest_respect_gitignore: bool,
+    /// Descartar ficheros y directorios ocultos.
+    pub ingest_skip_hidden: bool,
+    /// Qué hacer con los ficheros en los que se detectan secretos.
+    pub secret_policy: SecretPolicy,
+    /// Enlazar los ficheros de un repositorio git con su último commit y autor.
+    pub git_metadata: bool,
+    /// Directorio donde se guardan los ficheros subidos por `/api/upload`.
+    pub upload_dir: String,
+    /// Tamaño máximo de una petición de subida, en bytes.
+    pub upload_max_bytes: usize,
+    /// Datos personales que se sustituyen por marcadores; vacío = sin redacción.
+    pub pii_redaction: Vec<PiiKind>,
+    /// Guardar en `:Chunk.text` (y mostrar en las respuestas) los valores originales.
+    pub pii_restore_originals: bool,
+    /// Fichero local con la correspondencia marcador → valor original.
+    pub pii_mapping_path: String,
+
+    /// Tamaño máximo de cada chunk, en tokens.
+    pub chunk_size_tokens: usize,
+    /// Tokens que se repiten entre chunks consecutivos.
+    pub chunk_overlap_tokens: usize,
+
+    /// Campos de CSV/JSON/YAML que se convierten en entidades: (campo, etiqueta).
+    pub structured_entity_fields: Vec<(String, String)>,
+    /// Relaciones entre esos campos: (campo sujeto, predicado, campo objeto).
+    pub structured_relations: Vec<(String, String, String)>,
 }
 
 impl AppConfig {
+    /// Proveedor, modelo y dimensiones de los embeddings. Se guarda en cada
+    /// `:File`: si cambia, sus chunks se vuelven a calcular aunque el fichero no cambie.
+    pub fn embedding_signature(&self) -> String {
+        if self.llm_offline {
+            return format!("offline/{}", self.llm_embedding_dimensions);
+        }
+        format!("{:?}/{}/{}", self.embedding_provider, self.llm_embedding_model, self.llm_embedding_dimensions)
+    }
+
     /// Carga la configuración desde variables de entorno (usando .env si existe).
     pub fn from_env() -> Result<Self> {
         let neo4j_uri = env::var("NEO4J_URI")
@@ -50,20 +197,199 @@ impl AppConfig {
         let llm_provider_str =
             env::var("LLM_PROVIDER").unwrap_or_else(|_| "openai".to_string());
         let llm_provider = LlmProvider::from_str(&llm_provider_str)?;
+        let provider_or_default = |name: &str| match env::var(name).ok().filter(|raw| !raw.trim().is_empty()) {
+            Some(raw) => LlmProvider::from_str(raw.trim()),
+            None => Ok(llm_provider.clone()),
+        };
+        let embedding_provider = provider_or_default("EMBEDDING_PROVIDER")?;
+        let answer_provider = provider_or_default("ANSWER_PROVIDER")?;
+        let extraction_provider = provider_or_default("EXTRACTION_PROVIDER")?;
+
+        // Como `LLM_CHAT_MODEL`, `LLM_EMBEDDING_MODEL` y `LLM_EMBEDDING_DIMENSIONS`
+        // sólo valen si los embeddings siguen en `LLM_PROVIDER`; `EMBEDDING_MODEL`
+        // y `EMBEDDING_DIMENSIONS` acompañan a `EMBEDDING_PROVIDER`.
+        let embeddings_follow_llm = embedding_provider == llm_provider;
+        let llm_embedding_model = env_or_required(
+            "EMBEDDING_MODEL",
+            (if embeddings_follow_llm { env_or_optional("LLM_EMBEDDING_MODEL")? } else { None })
+                .or_else(|| embedding_provider.default_embedding_model().map(str::to_string)),
+        )?;
+        let llm_embedding_dimensions = env_or_required(
+            "EMBEDDING_DIMENSIONS",
+            (if embeddings_follow_llm { env_or_optional("LLM_EMBEDDING_DIMENSIONS")? } else { None })
+                .or_else(|| embedding_provider.default_embedding_dimensions()),
+        )?;
+        // `LLM_CHAT_MODEL` sólo vale para los usos que siguen en `LLM_PROVIDER`;
+        // el resto toma su modelo específico o el de su proveedor.
+        let llm_chat_model = env::var("LLM_CHAT_MODEL").ok().filter(|model| !model.trim().is_empty());
+        let chat_model_default = |provider: &LlmProvider| match &llm_chat_model {
+            Some(model) if *provider == llm_provider => Some(model.clone()),
+            _ => provider.default_chat_model().map(str::to_string),
+        };
+        let llm_answer_model = env_or_required("LLM_ANSWER_MODEL", chat_model_default(&answer_provider))?;
+        let llm_extraction_model =
+            env_or_required("LLM_EXTRACTION_MODEL", chat_model_default(&extraction_provider))?;
+        let ollama_base_url = env::var("OLLAMA_BASE_URL")
+            .unwrap_or_else(|_| "http://localhost:11434".to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let openai_compatible_base_url = env::var("OPENAI_COMPATIBLE_BASE_URL")
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_string();
+        let uses_openai_compatible = [&embedding_provider, &answer_provider, &extraction_provider]
+            .contains(&&LlmProvider::OpenAICompatible);
+        let llm_offline = env_or("LLM_OFFLINE", false)?;
+        let llm_offline_script = env::var("LLM_OFFLINE_SCRIPT").unwrap_or_default();
+        if uses_openai_compatible && openai_compatible_base_url.is_empty() && !llm_offline {
+            return Err(anyhow!("Falta OPENAI_COMPATIBLE_BASE_URL (p. ej. http://localhost:8000/v1) en el entorno"));
+        }
+        let openai_compatible_api_key = env::var("OPENAI_COMPATIBLE_API_KEY").unwrap_or_default();
+
+        let ingest_max_concurrent_files = env_or("INGEST_MAX_CONCURRENT_FILES", 4)?.max(1);
+        let ingest_max_llm_requests_per_file = env_or("INGEST_MAX_LLM_REQUESTS_PER_FILE", 4)?.max(1);
+        let ingest_max_llm_requests = env_or("INGEST_MAX_LLM_REQUESTS", 8)?.max(1);
+
+        let ingest_include = env_list("INGEST_INCLUDE", &[]);
+        let ingest_exclude = env_list("INGEST_EXCLUDE", &["**/target", "**/node_modules", "**/.git"]);
+        let ingest_max_file_size_bytes = env_or("INGEST_MAX_FILE_SIZE_BYTES", 20 * 1024 * 1024)?;
+        let ingest_max_archive_bytes = env_or("INGEST_MAX_ARCHIVE_BYTES", 512 * 1024 * 1024)?;
+        let ingest_respect_gitignore = env_or("INGEST_RESPECT_GITIGNORE", true)?;
+        let ingest_skip_hidden = env_or("INGEST_SKIP_HIDDEN", true)?;
+        let secret_policy =
+            SecretPolicy::from_str(&env::var("SECRET_POLICY").unwrap_or_else(|_| "skip".to_string()))?;
+        let git_metadata = env_or("GIT_METADATA", true)?;
+        let upload_dir = env::var("UPLOAD_DIR").unwrap_or_else(|_| ".nexusrag/uploads".to_string());
+        let upload_max_bytes = env_or("UPLOAD_MAX_BYTES", 200 * 1024 * 1024)?;
+        let pii_redaction = env_list("PII_REDACTION", &[])
+            .iter()
+            .map(|kind| PiiKind::from_str(kind))
+            .collect::<Result<Vec<_>>>()?;
+        let pii_restore_originals = env_or("PII_RESTORE_ORIGINALS", false)?;
+        let pii_mapping_path =
+            env::var("PII_MAPPING_PATH").unwrap_or_else(|_| ".nexusrag/pii_mapping.json".to_string());
+
+        let chunk_size_tokens = env_or("CHUNK_SIZE_TOKENS", 300)?.max(1);
+        let chunk_overlap_tokens = env_or("CHUNK_OVERLAP_TOKENS", 50)?;
+        if chunk_overlap_tokens >= chunk_size_tokens {
+            return Err(anyhow!(
+                "CHUNK_OVERLAP_TOKENS ({chunk_overlap_tokens}) debe ser menor que CHUNK_SIZE_TOKENS ({chunk_size_tokens})"
+            ));
+        }
 
-        let llm_embedding_model = env::var("LLM_EMBEDDING_MODEL")
-            .unwrap_or_else(|_| "text-embedding-3-small".to_string());
-        let llm_chat_model =
-            env::var("LLM_CHAT_MODEL").unwrap_or_else(|_| "gpt-4o-mini".to_string());
+        let structured_entity_fields = env_list("STRUCTURED_ENTITY_FIELDS", &[])
+            .into_iter()
+            .map(|item| match item.split_once(':') {
+                Some((field, label)) if !field.trim().is_empty() && !label.trim().is_empty() => {
+                    Ok((field.trim().to_string(), label.trim().to_string()))
+                }
+                _ => Err(anyhow!("Valor inválido en STRUCTURED_ENTITY_FIELDS (se espera campo:Etiqueta): {item}")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let structured_relations = env_list("STRUCTURED_RELATIONS", &[])
+            .into_iter()
+            .map(|item| {
+                let parts: Vec<&str> = item.split(':').map(str::trim).collect();
+                match parts.as_slice() {
+                    [subject, predicate, object]
+                        if [subject, object].iter().all(|field| {
+                            structured_entity_fields.iter().any(|(f, _)| f.eq_ignore_ascii_case(field))
+                        }) && !predicate.is_empty() =>
+                    {
+                        Ok((subject.to_string(), predicate.to_string(), object.to_string()))
+                    }
+                    _ => Err(anyhow!(
+                        "Valor inválido en STRUCTURED_RELATIONS (se espera sujeto:PREDICADO:objeto, con ambos campos en STRUCTURED_ENTITY_FIELDS): {item}"
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(Self {
             neo4j_uri,
             neo4j_user,
             neo4j_password,
             server_addr,
-            llm_provider,
+            embedding_provider,
+            answer_provider,
+            extraction_provider,
             llm_embedding_model,
-            llm_chat_model,
+            llm_embedding_dimensions,
+            llm_answer_model,
+            llm_extraction_model,
+            ollama_base_url,
+            openai_compatible_base_url,
+            openai_compatible_api_key,
+            llm_offline,
+            llm_offline_script,
+            ingest_max_concurrent_files,
+            ingest_max_llm_requests_per_file,
+            ingest_max_llm_requests,
+            ingest_include,
+            ingest_exclude,
+            ingest_max_file_size_bytes,
+            ingest_max_archive_bytes,
+            ingest_respect_gitignore,
+            ingest_skip_hidden,
+            secret_policy,
+            git_metadata,
+            upload_dir,
+            upload_max_bytes,
+            pii_redaction,
+            pii_restore_originals,
+            pii_mapping_path,
+            chunk_size_tokens,
+            chunk_overlap_tokens,
+            structured_entity_fields,
+            structured_relations,
         })
     }
 }
+
+/// Lee y parsea una variable de entorno opcional, usando `default` si no existe.
+fn env_or<T: FromStr>(name: &str, default: T) -> Result<T> {
+    match env::var(name) {
+        Ok(raw) => raw
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Valor inválido para {name}: {raw}")),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Como `env_or`, pero sin valor por defecto (`None`) la variable es obligatoria.
+/// Una variable vacía cuenta como no definida.
+fn env_or_required<T: FromStr>(name: &str, default: Option<T>) -> Result<T> {
+    match env::var(name).ok().filter(|raw| !raw.trim().is_empty()) {
+        Some(raw) => raw
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Valor inválido para {name}: {raw}")),
+        None => default.ok_or_else(|| anyhow!("Falta {name} en el entorno")),
+    }
+}
+
+/// Lee y parsea una variable de entorno opcional; `None` si no existe o está vacía.
+fn env_or_optional<T: FromStr>(name: &str) -> Result<Option<T>> {
+    match env::var(name).ok().filter(|raw| !raw.trim().is_empty()) {
+        Some(raw) => raw
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("Valor inválido para {name}: {raw}")),
+        None => Ok(None),
+    }
+}
+
+/// Lee una lista separada por comas, usando `default` si la variable no existe.
+fn env_list(name: &str, default: &[&str]) -> Vec<String> {
+    match env::var(name) {
+        Ok(raw) => raw
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(_) => default.iter().map(|item| item.to_string()).collect(),
+    }
+}
@@ -0,0 +1,480 @@
+//! Extracción de texto y metadatos de los formatos soportados en la ingesta:
+//! texto plano, HTML, PDF y formatos ofimáticos empaquetados en ZIP (DOCX, ODT, EPUB).
+
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    ops::Range,
+};
+
+use anyhow::{anyhow, Result};
+use quick_xml::{events::Event, Reader};
+use scraper::{node::Node, ElementRef, Html, Selector};
+use zip::ZipArchive;
+
+use crate::archive::Budget;
+
+/// Texto extraído de un documento junto con sus propiedades.
+#[derive(Debug, Default)]
+pub struct ExtractedDocument {
+    pub text: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Posición en bytes de `text` donde empieza cada página (la página `n`
+    /// empieza en `page_starts[n - 1]`). Vacío en formatos sin paginación.
+    pub page_starts: Vec<usize>,
+}
+
+impl ExtractedDocument {
+    /// Primera y última página (1-based) que cubre un tramo del texto.
+    pub fn page_range(&self, span: &Range<usize>) -> Option<(usize, usize)> {
+        if self.page_starts.is_empty() || span.is_empty() {
+            return None;
+        }
+        let page_at = |pos: usize| self.page_starts.partition_point(|&start| start <= pos).max(1);
+        Some((page_at(span.start), page_at(span.end - 1)))
+    }
+}
+
+/// Extensiones (en minúsculas) que la ingesta sabe leer.
+pub fn is_supported(extension: &str) -> bool {
+    matches!(
+        extension,
+        "pdf" | "docx" | "odt" | "epub" | "txt" | "md" | "rs" | "toml" | "log" | "html" | "htm" | "css" | "js"
+    )
+}
+
+/// Formatos cuyo texto extraído es el propio contenido del fichero, de modo
+/// que las posiciones en el texto son también posiciones (y líneas) del fichero.
+pub fn is_verbatim(extension: &str) -> bool {
+    matches!(
+        extension,
+        "txt" | "md" | "rs" | "toml" | "log" | "css" | "js" | "csv" | "json" | "jsonl" | "yaml" | "yml"
+    )
+}
+
+/// Extrae el texto (y, si el formato los tiene, título y autor) de un fichero
+/// según su extensión. Lo que se descomprime de DOCX, ODT y EPUB no pasa de `budget`.
+pub fn extract(extension: &str, bytes: Vec<u8>, budget: &mut Budget) -> Result<ExtractedDocument> {
+    match extension {
+        "pdf" => extract_pdf(&bytes),
+        "docx" => extract_docx(&bytes, budget),
+        "odt" => extract_odt(&bytes, budget),
+        "epub" => extract_epub(&bytes, budget),
+        "html" | "htm" => Ok(extract_html(&String::from_utf8_lossy(&bytes))),
+        _ => Ok(ExtractedDocument {
+            text: String::from_utf8(bytes).map_err(|_| anyhow!("el fichero no es texto UTF-8"))?,
+            ..Default::default()
+        }),
+    }
+}
+
+/// PDF página a página, para que cada chunk sepa de qué páginas procede.
+pub fn extract_pdf(bytes: &[u8]) -> Result<ExtractedDocument> {
+    let pages = pdf_extract::extract_text_from_mem_by_pages(bytes)?;
+    let mut text = String::new();
+    let mut page_starts = Vec::with_capacity(pages.len());
+    for page in pages {
+        if !text.is_empty() {
+            // Línea en blanco entre páginas: ningún párrafo cruza el salto.
+            text.push_str("\n\n");
+        }
+        page_starts.push(text.len());
+        text.push_str(page.trim());
+    }
+    Ok(ExtractedDocument { text, page_starts, ..Default::default() })
+}
+
+/// Word (Office Open XML): cuerpo en `word/document.xml`, propiedades en `docProps/core.xml`.
+pub fn extract_docx(bytes: &[u8], budget: &mut Budget) -> Result<ExtractedDocument> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let body = require_entry(&mut archive, "word/document.xml", budget)?;
+    let text = xml_paragraphs(&body, &XmlLayout {
+        paragraphs: &["p"],
+        tabs: &["tab"],
+        breaks: &["br", "cr"],
+        skipped: &["instrText", "delText"],
+    })?;
+
+    let (title, author) = match read_entry(&mut archive, "docProps/core.xml", budget)? {
+        Some(core) => (xml_first_text(&core, "title")?, xml_first_text(&core, "creator")?),
+        None => (None, None),
+    };
+    Ok(ExtractedDocument { text, title, author, ..Default::default() })
+}
+
+/// OpenDocument Text: cuerpo en `content.xml`, propiedades en `meta.xml`.
+pub fn extract_odt(bytes: &[u8], budget: &mut Budget) -> Result<ExtractedDocument> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let body = require_entry(&mut archive, "content.xml", budget)?;
+    let text = xml_paragraphs(&body, &XmlLayout {
+        paragraphs: &["p", "h"],
+        tabs: &["tab"],
+        breaks: &["line-break"],
+        skipped: &["tracked-changes", "annotation"],
+    })?;
+
+    let (title, author) = match read_entry(&mut archive, "meta.xml", budget)? {
+        Some(meta) => {
+            let author = match xml_first_text(&meta, "creator")? {
+                Some(author) => Some(author),
+                None => xml_first_text(&meta, "initial-creator")?,
+            };
+            (xml_first_text(&meta, "title")?, author)
+        }
+        None => (None, None),
+    };
+    Ok(ExtractedDocument { text, title, author, ..Default::default() })
+}
+
+/// EPUB: el fichero OPF indicado en `META-INF/container.xml` da los metadatos
+/// y el orden de lectura (`spine`) de los documentos XHTML.
+pub fn extract_epub(bytes: &[u8], budget: &mut Budget) -> Result<ExtractedDocument> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let container = require_entry(&mut archive, "META-INF/container.xml", budget)?;
+    let opf_path = xml_first_attr(&container, "rootfile", "full-path")?
+        .ok_or_else(|| anyhow!("EPUB sin 'rootfile' en META-INF/container.xml"))?;
+    let opf = require_entry(&mut archive, &opf_path, budget)?;
+    let base_dir = opf_path.rsplit_once('/').map(|(dir, _)| format!("{dir}/")).unwrap_or_default();
+
+    let manifest = epub_manifest(&opf)?;
+    let spine = epub_spine(&opf)?;
+
+    let layout = XmlLayout {
+        paragraphs: &["p", "h1", "h2", "h3", "h4", "h5", "h6", "li", "blockquote", "pre", "dt", "dd", "tr"],
+        tabs: &["td", "th"],
+        breaks: &["br"],
+        skipped: &["head", "script", "style", "nav"],
+    };
+    let mut sections = Vec::new();
+    for idref in spine {
+        let Some(href) = manifest.get(&idref) else { continue };
+        let path = format!("{base_dir}{}", href.split('#').next().unwrap_or(href));
+        let Some(xhtml) = read_entry(&mut archive, &path, budget)? else { continue };
+        let section = xml_paragraphs(&xhtml, &layout)?;
+        if !section.is_empty() {
+            sections.push(section);
+        }
+    }
+
+    Ok(ExtractedDocument {
+        text: sections.join("\n\n"),
+        title: xml_first_text(&opf, "title")?,
+        author: xml_first_text(&opf, "creator")?,
+        ..Default::default()
+    })
+}
+
+/// Elementos HTML cuyo contenido no forma parte del texto del documento:
+/// código, estilos y la navegación o el "boilerplate" de la página.
+const HTML_SKIPPED: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "iframe", "nav", "header", "footer",
+    "aside", "form", "button",
+];
+
+/// Elementos HTML que delimitan un bloque de texto propio.
+const HTML_BLOCKS: &[&str] = &[
+    "p", "div", "section", "article", "main", "blockquote", "pre", "li", "dt", "dd", "tr", "table",
+    "ul", "ol", "dl", "figure", "figcaption", "caption", "address", "details", "summary", "hr",
+];
+
+/// HTML: descarta scripts, estilos y navegación y conserva encabezados y
+/// párrafos. Los encabezados se emiten como `#` de Markdown para que el
+/// chunker los use como secciones; el título sale de `<title>` (o del primer `<h1>`).
+pub fn extract_html(html: &str) -> ExtractedDocument {
+    let document = Html::parse_document(html);
+    let first_text = |selector: &str| {
+        let selector = Selector::parse(selector).ok()?;
+        let element = document.select(&selector).next()?;
+        let text = element.text().collect::<Vec<_>>().join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    };
+
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    html_blocks(document.root_element(), &mut current, &mut paragraphs);
+    flush_paragraph(&mut current, &mut paragraphs);
+
+    ExtractedDocument {
+        text: paragraphs.join("\n\n"),
+        title: first_text("title").or_else(|| first_text("h1")),
+        author: Selector::parse("meta[name=author]").ok().and_then(|selector| {
+            let content = document.select(&selector).next()?.value().attr("content")?.trim();
+            (!content.is_empty()).then(|| content.to_string())
+        }),
+        ..Default::default()
+    }
+}
+
+fn html_blocks(element: ElementRef, current: &mut String, paragraphs: &mut Vec<String>) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => current.push_str(text),
+            Node::Element(el) => {
+                let Some(child) = ElementRef::wrap(child) else { continue };
+                let name = el.name();
+                let hidden = el.attr("hidden").is_some()
+                    || el.attr("aria-hidden") == Some("true")
+                    || matches!(el.attr("role"), Some("navigation" | "banner" | "contentinfo"));
+                if hidden || HTML_SKIPPED.contains(&name) {
+                    continue;
+                }
+                if let Some(level) = heading_level(name) {
+                    flush_paragraph(current, paragraphs);
+                    html_blocks(child, current, paragraphs);
+                    // Los encabezados vacíos no se emiten.
+                    if !current.trim().is_empty() {
+                        current.insert_str(0, &format!("{} ", "#".repeat(level)));
+                    }
+                    flush_paragraph(current, paragraphs);
+                } else if name == "br" {
+                    current.push('\n');
+                } else if matches!(name, "td" | "th") {
+                    current.push('\t');
+                    html_blocks(child, current, paragraphs);
+                } else if HTML_BLOCKS.contains(&name) {
+                    flush_paragraph(current, paragraphs);
+                    html_blocks(child, current, paragraphs);
+                    flush_paragraph(current, paragraphs);
+                } else {
+                    html_blocks(child, current, paragraphs);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn heading_level(name: &str) -> Option<usize> {
+    match name {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// Lee una entrada del contenedor sin pasar de `budget`: el tamaño declarado
+/// no es de fiar y unos pocos KB pueden descomprimirse en gigabytes. `None`
+/// si la entrada no existe.
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str, budget: &mut Budget) -> Result<Option<String>> {
+    let Ok(entry) = archive.by_name(name) else { return Ok(None) };
+    let bytes = budget
+        .read(entry, name)?
+        .ok_or_else(|| anyhow!("'{name}' supera el tamaño máximo descomprimido"))?;
+    Ok(Some(String::from_utf8(bytes).map_err(|_| anyhow!("'{name}' no es texto UTF-8"))?))
+}
+
+fn require_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str, budget: &mut Budget) -> Result<String> {
+    read_entry(archive, name, budget)?.ok_or_else(|| anyhow!("Falta '{name}' en el contenedor"))
+}
+
+/// Qué elementos (por nombre local) delimitan párrafos, tabulaciones y saltos,
+/// y cuáles se ignoran por completo.
+struct XmlLayout<'a> {
+    paragraphs: &'a [&'a str],
+    tabs: &'a [&'a str],
+    breaks: &'a [&'a str],
+    skipped: &'a [&'a str],
+}
+
+/// Recorre un XML y devuelve su texto con un párrafo por bloque, separados por
+/// líneas en blanco para que el chunker respete la estructura.
+fn xml_paragraphs(xml: &str, layout: &XmlLayout) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    let mut skip_depth = 0usize;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let name = e.local_name();
+                let name = std::str::from_utf8(name.as_ref()).unwrap_or_default();
+                if skip_depth > 0 || layout.skipped.contains(&name) {
+                    skip_depth += 1;
+                } else if layout.paragraphs.contains(&name) {
+                    flush_paragraph(&mut current, &mut paragraphs);
+                } else if layout.tabs.contains(&name) {
+                    current.push('\t');
+                }
+            }
+            Event::Empty(e) => {
+                let name = e.local_name();
+                let name = std::str::from_utf8(name.as_ref()).unwrap_or_default();
+                if skip_depth > 0 {
+                    continue;
+                }
+                if layout.tabs.contains(&name) {
+                    current.push('\t');
+                } else if layout.breaks.contains(&name) {
+                    current.push('\n');
+                } else if name == "s" {
+                    // <text:s/> en ODT representa espacios repetidos.
+                    current.push(' ');
+                }
+            }
+            Event::End(e) => {
+                let name = e.local_name();
+                let name = std::str::from_utf8(name.as_ref()).unwrap_or_default();
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                } else if layout.paragraphs.contains(&name) {
+                    flush_paragraph(&mut current, &mut paragraphs);
+                }
+            }
+            Event::Text(t) if skip_depth == 0 => match t.unescape_with(html_entity) {
+                Ok(text) => current.push_str(&text),
+                Err(_) => current.push_str(&String::from_utf8_lossy(&t)),
+            },
+            Event::CData(t) if skip_depth == 0 => current.push_str(&String::from_utf8_lossy(&t)),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    flush_paragraph(&mut current, &mut paragraphs);
+    Ok(paragraphs.join("\n\n"))
+}
+
+/// Entidades HTML habituales en XHTML que no están predefinidas en XML.
+fn html_entity(entity: &str) -> Option<&'static str> {
+    match entity {
+        "nbsp" => Some("\u{a0}"),
+        "ndash" => Some("–"),
+        "mdash" => Some("—"),
+        "hellip" => Some("…"),
+        "lsquo" => Some("‘"),
+        "rsquo" => Some("’"),
+        "ldquo" => Some("“"),
+        "rdquo" => Some("”"),
+        "laquo" => Some("«"),
+        "raquo" => Some("»"),
+        "copy" => Some("©"),
+        "reg" => Some("®"),
+        _ => None,
+    }
+}
+
+fn flush_paragraph(current: &mut String, paragraphs: &mut Vec<String>) {
+    let paragraph = current.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !paragraph.is_empty() {
+        paragraphs.push(paragraph);
+    }
+    current.clear();
+}
+
+/// Texto del primer elemento con ese nombre local (p. ej. `dc:title`).
+fn xml_first_text(xml: &str, element: &str) -> Result<Option<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut inside = false;
+    let mut value = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == element.as_bytes() => inside = true,
+            Event::End(e) if inside && e.local_name().as_ref() == element.as_bytes() => break,
+            Event::Text(t) if inside => value.push_str(&t.unescape()?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let value = value.trim();
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
+
+/// Valor de un atributo del primer elemento con ese nombre local.
+fn xml_first_attr(xml: &str, element: &str, attribute: &str) -> Result<Option<String>> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == element.as_bytes() => {
+                for attr in e.attributes().flatten() {
+                    if attr.key.local_name().as_ref() == attribute.as_bytes() {
+                        return Ok(Some(attr.unescape_value()?.into_owned()));
+                    }
+                }
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// `id` → `href` de los elementos del manifiesto del OPF.
+fn epub_manifest(opf: &str) -> Result<HashMap<String, String>> {
+    let mut reader = Reader::from_str(opf);
+    let mut manifest = HashMap::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"item" => {
+                let mut id = None;
+                let mut href = None;
+                for attr in e.attributes().flatten() {
+                    match attr.key.local_name().as_ref() {
+                        b"id" => id = Some(attr.unescape_value()?.into_owned()),
+                        b"href" => href = Some(attr.unescape_value()?.into_owned()),
+                        _ => {}
+                    }
+                }
+                if let (Some(id), Some(href)) = (id, href) {
+                    manifest.insert(id, href);
+                }
+            }
+            Event::Eof => return Ok(manifest),
+            _ => {}
+        }
+    }
+}
+
+/// Ids del `spine` del OPF, en orden de lectura.
+fn epub_spine(opf: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(opf);
+    let mut spine = Vec::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"itemref" => {
+                for attr in e.attributes().flatten() {
+                    if attr.key.local_name().as_ref() == b"idref" {
+                        spine.push(attr.unescape_value()?.into_owned());
+                    }
+                }
+            }
+            Event::Eof => return Ok(spine),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn docx(body: &str) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("word/document.xml", SimpleFileOptions::default()).unwrap();
+        zip.write_all(body.as_bytes()).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn packaged_documents_are_read_within_the_budget() {
+        let body = format!("<w:document><w:p>{}</w:p></w:document>", "a".repeat(4096));
+        let bytes = docx(&body);
+
+        let extracted = extract("docx", bytes.clone(), &mut Budget::new(0, 0)).unwrap();
+        assert_eq!(extracted.text.len(), 4096);
+
+        // Unos pocos bytes comprimidos no pueden descomprimirse sin límite.
+        assert!(bytes.len() < 1024);
+        assert!(extract("docx", bytes.clone(), &mut Budget::new(1024, 0)).is_err());
+        assert!(extract("docx", bytes, &mut Budget::new(0, 1024)).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Selección de los ficheros que entran en la ingesta: patrones de inclusión
+//! y exclusión, tamaño máximo, ficheros ocultos y reglas de `.gitignore`/`.ignore`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::{archive, config::AppConfig};
+
+/// Reglas de selección de ficheros. Los patrones se aplican a la ruta
+/// relativa a la raíz de la ingesta (p. ej. `docs/**/*.md`).
+#[derive(Debug, Clone)]
+pub struct FilterRules {
+    /// Si no está vacío, sólo se ingieren los ficheros que encajen en algún patrón.
+    pub include: Vec<String>,
+    /// Ficheros y directorios que se descartan (un directorio descarta todo su contenido).
+    pub exclude: Vec<String>,
+    /// Tamaño máximo en bytes; 0 significa sin límite.
+    pub max_file_size_bytes: u64,
+    /// Tamaño máximo de un archivo comprimido en bytes; 0 significa sin límite.
+    pub max_archive_bytes: u64,
+    /// Respetar los `.gitignore` e `.ignore` que haya bajo la raíz.
+    pub respect_gitignore: bool,
+    /// Descartar ficheros y directorios ocultos (nombre que empieza por `.`).
+    pub skip_hidden: bool,
+}
+
+impl FilterRules {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            include: cfg.ingest_include.clone(),
+            exclude: cfg.ingest_exclude.clone(),
+            max_file_size_bytes: cfg.ingest_max_file_size_bytes,
+            max_archive_bytes: cfg.ingest_max_archive_bytes,
+            respect_gitignore: cfg.ingest_respect_gitignore,
+            skip_hidden: cfg.ingest_skip_hidden,
+        }
+    }
+}
+
+/// Reglas ya compiladas.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    max_file_size_bytes: u64,
+    max_archive_bytes: u64,
+    respect_gitignore: bool,
+    skip_hidden: bool,
+}
+
+impl FileFilter {
+    pub fn new(rules: &FilterRules) -> Result<Self> {
+        Ok(Self {
+            include: if rules.include.is_empty() { None } else { Some(build_globset(&rules.include)?) },
+            exclude: build_globset(&rules.exclude)?,
+            max_file_size_bytes: rules.max_file_size_bytes,
+            max_archive_bytes: rules.max_archive_bytes,
+            respect_gitignore: rules.respect_gitignore,
+            skip_hidden: rules.skip_hidden,
+        })
+    }
+
+    /// Tamaño máximo de fichero en bytes (0 = sin límite).
+    pub fn max_file_size_bytes(&self) -> u64 {
+        self.max_file_size_bytes
+    }
+
+    /// Recorre `root` y devuelve los ficheros aceptados junto con el número de
+    /// rutas descartadas. Los directorios descartados no se recorren (y
+    /// cuentan como una sola ruta).
+    pub fn walk(&self, root: &Path) -> (Vec<PathBuf>, u32) {
+        self.walk_dir(root, root)
+    }
+
+    /// Como `walk`, pero recorre sólo `dir`, un directorio bajo `root` (p. ej.
+    /// uno creado en modo vigilancia). Patrones y `.gitignore` de los
+    /// directorios intermedios se evalúan como en un recorrido desde `root`.
+    pub fn walk_dir(&self, root: &Path, dir: &Path) -> (Vec<PathBuf>, u32) {
+        let mut files = Vec::new();
+        let mut filtered = 0;
+        // Reglas de ignorado de los directorios antecesores de la entrada actual.
+        let mut ignores: Vec<(PathBuf, Gitignore)> = Vec::new();
+        if self.respect_gitignore && dir != root {
+            let mut ancestors: Vec<&Path> =
+                dir.ancestors().skip(1).take_while(|ancestor| ancestor.starts_with(root)).collect();
+            ancestors.reverse();
+            for ancestor in ancestors {
+                ignores.extend(load_ignore_files(ancestor).map(|g| (ancestor.to_path_buf(), g)));
+            }
+        }
+
+        let mut walker = WalkDir::new(dir).into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else { continue };
+            let path = entry.path();
+            let is_dir = entry.file_type().is_dir();
+            ignores.retain(|(dir, _)| path.starts_with(dir));
+
+            if entry.depth() > 0 {
+                let size = if is_dir { 0 } else { entry.metadata().map(|m| m.len()).unwrap_or(0) };
+                if let Some(reason) = self.exclusion(root, path, is_dir, size, &ignores) {
+                    debug!("Ruta excluida ({}): {}", reason, path.display());
+                    filtered += 1;
+                    if is_dir {
+                        walker.skip_current_dir();
+                    }
+                    continue;
+                }
+            }
+
+            if is_dir {
+                if self.respect_gitignore {
+                    if let Some(gitignore) = load_ignore_files(path) {
+                        ignores.push((path.to_path_buf(), gitignore));
+                    }
+                }
+            } else if entry.file_type().is_file() {
+                files.push(path.to_path_buf());
+            }
+        }
+        (files, filtered)
+    }
+
+    /// Comprueba una ruta suelta bajo `root` (modo vigilancia), incluidos sus
+    /// directorios antecesores. Devuelve el motivo si se descarta.
+    pub fn excludes(&self, root: &Path, path: &Path) -> Option<&'static str> {
+        let relative = path.strip_prefix(root).ok()?;
+        let mut ignores: Vec<(PathBuf, Gitignore)> = Vec::new();
+        let mut current = root.to_path_buf();
+        if self.respect_gitignore {
+            ignores.extend(load_ignore_files(&current).map(|g| (current.clone(), g)));
+        }
+
+        let components: Vec<_> = relative.components().collect();
+        for (i, component) in components.iter().enumerate() {
+            current.push(component);
+            let is_last = i + 1 == components.len();
+            let is_dir = !is_last || current.is_dir();
+            let size = if is_dir { 0 } else { current.metadata().map(|m| m.len()).unwrap_or(0) };
+            if let Some(reason) = self.exclusion(root, &current, is_dir, size, &ignores) {
+                return Some(reason);
+            }
+            if is_dir && self.respect_gitignore {
+                ignores.extend(load_ignore_files(&current).map(|g| (current.clone(), g)));
+            }
+        }
+        None
+    }
+
+    /// Comprueba un miembro de un archivo comprimido por su ruta interna. Los
+    /// `.gitignore` no se aplican dentro del archivo.
+    pub fn excludes_member(&self, root: &Path, archive: &Path, inner_path: &str, size: u64) -> Option<&'static str> {
+        let mut current = PathBuf::from(format!("{}!", archive.display()));
+        let components: Vec<&str> = inner_path.split('/').filter(|c| !c.is_empty()).collect();
+        for (i, component) in components.iter().enumerate() {
+            current.push(component);
+            let is_dir = i + 1 < components.len();
+            if let Some(reason) = self.exclusion(root, &current, is_dir, size, &[]) {
+                return Some(reason);
+            }
+        }
+        None
+    }
+
+    fn exclusion(
+        &self,
+        root: &Path,
+        path: &Path,
+        is_dir: bool,
+        size: u64,
+        ignores: &[(PathBuf, Gitignore)],
+    ) -> Option<&'static str> {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if self.skip_hidden && hidden {
+            return Some("oculto");
+        }
+        if self.exclude.is_match(relative) {
+            return Some("patrón de exclusión");
+        }
+        // El `.gitignore` más cercano manda; una regla `!patrón` vuelve a incluir.
+        for (_, gitignore) in ignores.iter().rev() {
+            match gitignore.matched(path, is_dir) {
+                Match::Ignore(_) => return Some(".gitignore"),
+                Match::Whitelist(_) => break,
+                Match::None => {}
+            }
+        }
+        if is_dir {
+            return None;
+        }
+        // Los archivos comprimidos se recorren como directorios: la inclusión
+        // y el tamaño máximo se aplican a sus miembros. El archivo se carga
+        // entero en memoria, así que no puede ocupar más que lo que se
+        // permite descomprimir de él.
+        if archive::is_archive(path) {
+            return (self.max_archive_bytes > 0 && size > self.max_archive_bytes).then_some("archivo demasiado grande");
+        }
+        if let Some(include) = &self.include {
+            if !include.is_match(relative) {
+                return Some("fuera de los patrones de inclusión");
+            }
+        }
+        if self.max_file_size_bytes > 0 && size > self.max_file_size_bytes {
+            return Some("tamaño máximo superado");
+        }
+        None
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| anyhow!("Patrón inválido '{pattern}': {e}"))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// Reglas de `.gitignore` e `.ignore` de un directorio, si tiene alguno.
+fn load_ignore_files(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in [".gitignore", ".ignore"] {
+        let file = dir.join(name);
+        if file.is_file() {
+            if let Some(err) = builder.add(&file) {
+                debug!("Error leyendo {}: {}", file.display(), err);
+            }
+            found = true;
+        }
+    }
+    if !found {
+        return None;
+    }
+    builder.build().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Árbol de prueba en un directorio temporal propio.
+    fn tree(files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("nexusrag-filter-{}", uuid::Uuid::new_v4()));
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    fn rules() -> FilterRules {
+        FilterRules {
+            include: Vec::new(),
+            exclude: vec!["**/target".into()],
+            max_file_size_bytes: 0,
+            max_archive_bytes: 0,
+            respect_gitignore: true,
+            skip_hidden: true,
+        }
+    }
+
+    fn relative(root: &Path, files: Vec<PathBuf>) -> Vec<String> {
+        let mut files: Vec<String> =
+            files.iter().map(|f| f.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/")).collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn walk_honours_gitignore_exclusions_and_hidden_files() {
+        let root = tree(&[
+            (".gitignore", "*.log\nbuild/\n"),
+            ("docs/.gitignore", "!keep.log\nborrador.md\n"),
+            ("docs/guia.md", "guía"),
+            ("docs/borrador.md", "borrador"),
+            ("docs/keep.log", "se queda"),
+            ("app.log", "fuera"),
+            ("build/out.txt", "fuera"),
+            ("target/debug/notas.txt", "fuera"),
+            (".env", "SECRETO=1"),
+            (".cache/datos.txt", "fuera"),
+            ("src/main.rs", "fn main() {}"),
+        ]);
+        let filter = FileFilter::new(&rules()).unwrap();
+
+        let (files, filtered) = filter.walk(&root);
+        assert_eq!(relative(&root, files), ["docs/guia.md", "docs/keep.log", "src/main.rs"]);
+        // `.gitignore` x2, `.env`, `.cache`, `app.log`, `build`, `target` y `borrador.md`.
+        assert_eq!(filtered, 8);
+
+        // Recorrer un subdirectorio aplica los `.gitignore` de sus antecesores.
+        fs::create_dir_all(root.join("docs/nuevo")).unwrap();
+        fs::write(root.join("docs/nuevo/traza.log"), "fuera").unwrap();
+        fs::write(root.join("docs/nuevo/borrador.md"), "fuera").unwrap();
+        fs::write(root.join("docs/nuevo/tema.md"), "dentro").unwrap();
+        let (files, _) = filter.walk_dir(&root, &root.join("docs/nuevo"));
+        assert_eq!(relative(&root, files), ["docs/nuevo/tema.md"]);
+
+        assert_eq!(filter.excludes(&root, &root.join("build/out.txt")), Some(".gitignore"));
+        assert_eq!(filter.excludes(&root, &root.join("target/debug/notas.txt")), Some("patrón de exclusión"));
+        assert_eq!(filter.excludes(&root, &root.join(".cache/datos.txt")), Some("oculto"));
+        assert_eq!(filter.excludes(&root, &root.join("docs/guia.md")), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn include_patterns_and_size_apply_to_files_and_archive_members() {
+        let root = tree(&[
+            ("docs/a.md", "corto"),
+            ("docs/b.md", &"x".repeat(100)),
+            ("notas.txt", "fuera"),
+            // Los archivos se abren aunque no encajen en la inclusión, si no superan su límite.
+            ("datos.zip", &"x".repeat(100)),
+            ("grande.tar", &"x".repeat(200)),
+        ]);
+        let filter = FileFilter::new(&FilterRules {
+            include: vec!["docs/**/*.md".into()],
+            max_file_size_bytes: 50,
+            max_archive_bytes: 150,
+            respect_gitignore: false,
+            skip_hidden: false,
+            ..rules()
+        })
+        .unwrap();
+
+        let (files, filtered) = filter.walk(&root);
+        assert_eq!(relative(&root, files), ["datos.zip", "docs/a.md"]);
+        assert_eq!(filtered, 3);
+        assert_eq!(filter.excludes(&root, &root.join("grande.tar")), Some("archivo demasiado grande"));
+
+        let archive = root.join("docs/paquete.zip");
+        assert_eq!(filter.excludes_member(&root, &archive, "c.md", 10), None);
+        assert_eq!(filter.excludes_member(&root, &archive, "c.txt", 10), Some("fuera de los patrones de inclusión"));
+        assert_eq!(filter.excludes_member(&root, &archive, "c.md", 100), Some("tamaño máximo superado"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
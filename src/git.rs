@@ -0,0 +1,224 @@
+//! Metadatos de git de los ficheros ingeridos: commit en el que se ingirieron,
+//! último commit y autor de cada fichero (nodos `:Commit` y `:Person`), y
+//! ficheros cambiados entre dos revisiones para re-ingerir sólo esos.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use git2::{Commit, Repository, Sort};
+use neo4rs::{query, Graph};
+
+/// Un commit con su autor.
+#[derive(Debug, Clone)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub date: DateTime<Utc>,
+    /// Primera línea del mensaje.
+    pub summary: String,
+}
+
+impl CommitInfo {
+    fn from_commit(commit: &Commit) -> Self {
+        let author = commit.author();
+        Self {
+            hash: commit.id().to_string(),
+            author_name: author.name().unwrap_or_default().to_string(),
+            author_email: author.email().unwrap_or_default().to_lowercase(),
+            date: DateTime::<Utc>::from_timestamp(commit.time().seconds(), 0).unwrap_or_default(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+/// Último commit de cada fichero de un repositorio.
+#[derive(Debug)]
+pub struct RepoHistory {
+    /// Commit `HEAD` en el momento de la ingesta.
+    pub head: String,
+    /// (id del `:File`, último commit que lo modificó).
+    pub last_commits: Vec<(String, CommitInfo)>,
+}
+
+/// Abre el repositorio que contiene `root` y devuelve también su directorio de
+/// trabajo y `root`, ambos canónicos.
+fn open(root: &Path) -> Result<Option<(Repository, PathBuf, PathBuf)>> {
+    let Ok(repo) = Repository::discover(root) else { return Ok(None) };
+    let Some(workdir) = repo.workdir() else { return Ok(None) };
+    let workdir = fs::canonicalize(workdir)?;
+    let canonical_root = fs::canonicalize(root)?;
+    Ok(Some((repo, workdir, canonical_root)))
+}
+
+/// Busca en la historia de `HEAD` el último commit que tocó cada fichero de
+/// `files` (rutas bajo `root`, tal como se guardan en `:File.id`). `None` si
+/// `root` no está en un repositorio git o éste no tiene commits. Los ficheros
+/// que no están en `HEAD` (sin seguimiento o aún sin commit) se omiten antes
+/// de recorrer la historia, que si no se recorrería entera buscándolos.
+pub fn last_commits(root: &Path, files: &[PathBuf]) -> Result<Option<RepoHistory>> {
+    let Some((repo, workdir, canonical_root)) = open(root)? else { return Ok(None) };
+    let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else { return Ok(None) };
+
+    // Ruta relativa al repositorio → id del `:File`.
+    let head_tree = head.tree()?;
+    let mut pending: HashMap<PathBuf, String> = files
+        .iter()
+        .filter_map(|file| {
+            let relative_to_root = file.strip_prefix(root).ok()?;
+            let relative = canonical_root.join(relative_to_root).strip_prefix(&workdir).ok()?.to_path_buf();
+            head_tree.get_path(&relative).ok()?;
+            Some((relative, file.to_string_lossy().to_string()))
+        })
+        .collect();
+
+    let mut last_commits = Vec::new();
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head.id())?;
+    revwalk.set_sorting(Sort::TIME)?;
+    for oid in revwalk {
+        if pending.is_empty() {
+            break;
+        }
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+        // En los merges se compara con el primer padre: los cambios traídos de
+        // la otra rama se atribuyen al merge.
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        let mut info = None;
+        for delta in diff.deltas() {
+            let Some(path) = delta.new_file().path() else { continue };
+            if let Some(file_id) = pending.remove(path) {
+                let info = info.get_or_insert_with(|| CommitInfo::from_commit(&commit));
+                last_commits.push((file_id, info.clone()));
+            }
+        }
+    }
+
+    Ok(Some(RepoHistory { head: head.id().to_string(), last_commits }))
+}
+
+/// Ficheros bajo `root` añadidos, modificados, renombrados o borrados entre
+/// las revisiones `from` y `to` (cualquier expresión que entienda git:
+/// `HEAD~3`, una rama, un hash...). Las rutas se devuelven bajo `root`.
+/// Los ficheros se leen de la copia de trabajo, así que `to` tiene que ser
+/// el commit de `HEAD`.
+pub fn changed_paths(root: &Path, from: &str, to: &str) -> Result<Vec<PathBuf>> {
+    let (repo, workdir, canonical_root) =
+        open(root)?.ok_or_else(|| anyhow!("{} no está dentro de un repositorio git", root.display()))?;
+    let commit = |rev: &str| -> Result<Commit> {
+        repo.revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| anyhow!("Revisión inválida '{rev}': {}", e.message()))
+    };
+    let (from, to_commit) = (commit(from)?, commit(to)?);
+    if to_commit.id() != commit("HEAD")?.id() {
+        return Err(anyhow!(
+            "'{to}' no es el commit de HEAD: sólo se puede re-ingerir hasta HEAD, porque los ficheros se leen de la copia de trabajo"
+        ));
+    }
+    let diff = repo.diff_tree_to_tree(Some(&from.tree()?), Some(&to_commit.tree()?), None)?;
+
+    let mut paths = BTreeSet::new();
+    for delta in diff.deltas() {
+        for path in [delta.old_file().path(), delta.new_file().path()].into_iter().flatten() {
+            if let Ok(relative) = workdir.join(path).strip_prefix(&canonical_root) {
+                paths.insert(root.join(relative));
+            }
+        }
+    }
+    Ok(paths.into_iter().collect())
+}
+
+/// Guarda en cada `:File` el commit de la ingesta y lo enlaza con su último
+/// commit y el autor de éste:
+/// `(:File)-[:LAST_CHANGED_IN]->(:Commit)-[:AUTHORED_BY]->(:Person)`.
+pub async fn record_history(graph: &Graph, history: &RepoHistory) -> Result<()> {
+    if history.last_commits.is_empty() {
+        return Ok(());
+    }
+    let commits: Vec<&CommitInfo> = history.last_commits.iter().map(|(_, commit)| commit).collect();
+    let file_ids: Vec<String> = history.last_commits.iter().map(|(id, _)| id.clone()).collect();
+
+    graph.run(
+        query("MATCH (f:File)-[r:LAST_CHANGED_IN]->(:Commit) WHERE f.id IN $ids DELETE r")
+        .param("ids", file_ids.clone()),
+    ).await?;
+
+    graph.run(
+        query(
+            "UNWIND range(0, size($ids) - 1) AS i
+             MATCH (f:File {id: $ids[i]})
+             MERGE (c:Commit {hash: $hashes[i]})
+             SET c.date = datetime($dates[i]), c.message = $messages[i]
+             MERGE (p:Person {email: $emails[i]})
+             SET p.name = $names[i]
+             MERGE (c)-[:AUTHORED_BY]->(p)
+             MERGE (f)-[:LAST_CHANGED_IN]->(c)
+             SET f.git_commit = $head, f.last_author = $names[i], f.last_commit_date = datetime($dates[i])"
+        )
+        .param("ids", file_ids)
+        .param("hashes", commits.iter().map(|c| c.hash.clone()).collect::<Vec<String>>())
+        .param("dates", commits.iter().map(|c| c.date.to_rfc3339()).collect::<Vec<String>>())
+        .param("messages", commits.iter().map(|c| c.summary.clone()).collect::<Vec<String>>())
+        .param("emails", commits.iter().map(|c| c.author_email.clone()).collect::<Vec<String>>())
+        .param("names", commits.iter().map(|c| c.author_name.clone()).collect::<Vec<String>>())
+        .param("head", history.head.clone()),
+    ).await?;
+
+    // Commits y autores que ya no son el último cambio de ningún fichero.
+    graph.run(query(
+        "MATCH (c:Commit) WHERE NOT ()-[:LAST_CHANGED_IN]->(c)
+         OPTIONAL MATCH (c)-[:AUTHORED_BY]->(p:Person)
+         DETACH DELETE c
+         WITH DISTINCT p WHERE p IS NOT NULL AND NOT p:Entity AND NOT (:Commit)-[:AUTHORED_BY]->(p)
+         DETACH DELETE p"
+    )).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::Signature;
+
+    use super::*;
+
+    fn commit_file(repo: &Repository, name: &str, content: &str) {
+        fs::write(repo.workdir().unwrap().join(name), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Ana", "ana@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        repo.commit(Some("HEAD"), &signature, &signature, name, &tree, parent.as_ref().into_iter().collect::<Vec<_>>().as_slice())
+            .unwrap();
+    }
+
+    #[test]
+    fn changed_paths_only_reach_head() {
+        let root = std::env::temp_dir().join(format!("nexusrag-git-{}", uuid::Uuid::new_v4()));
+        let repo = Repository::init(&root).unwrap();
+        commit_file(&repo, "a.md", "uno");
+        commit_file(&repo, "b.md", "dos");
+        commit_file(&repo, "a.md", "tres");
+
+        assert_eq!(changed_paths(&root, "HEAD~2", "HEAD").unwrap(), [root.join("a.md"), root.join("b.md")]);
+        // Una rama que apunta al mismo commit vale como `HEAD`.
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        assert_eq!(changed_paths(&root, "HEAD~1", &branch).unwrap(), [root.join("a.md")]);
+        // Los ficheros de `HEAD~1` ya no están en la copia de trabajo.
+        let err = changed_paths(&root, "HEAD~2", "HEAD~1").unwrap_err();
+        assert!(err.to_string().contains("no es el commit de HEAD"), "{err}");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
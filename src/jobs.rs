@@ -0,0 +1,170 @@
+//! Gestor de trabajos de ingesta: identificadores, cancelación e historial.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use chrono::Utc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::ingest::IngestionSummary;
+
+/// Número máximo de trabajos terminados que se conservan en el historial.
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Información pública de un trabajo de ingesta.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub root: PathBuf,
+    pub state: JobState,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub summary: Option<IngestionSummary>,
+    /// `:Document` creados por una subida, cuando su ingesta termina bien.
+    pub document_ids: Option<Vec<String>>,
+    pub error: Option<String>,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct JobRegistry {
+    entries: HashMap<String, JobEntry>,
+    /// Ids en orden de creación, para recortar el historial.
+    order: VecDeque<String>,
+}
+
+/// Registro compartido de trabajos. Sólo puede haber uno en ejecución.
+#[derive(Clone, Default)]
+pub struct JobManager {
+    registry: Arc<Mutex<JobRegistry>>,
+}
+
+impl JobManager {
+    /// Registra un nuevo trabajo sobre `root` y devuelve su id junto con la
+    /// bandera de cancelación. Si ya hay uno en marcha devuelve su id como error.
+    pub fn start(&self, root: PathBuf) -> Result<(String, Arc<AtomicBool>), String> {
+        let mut registry = self.registry.lock().unwrap();
+        if let Some(running) = registry
+            .entries
+            .values()
+            .find(|entry| entry.info.state == JobState::Running)
+        {
+            return Err(running.info.id.clone());
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let cancel = Arc::new(AtomicBool::new(false));
+        registry.entries.insert(
+            id.clone(),
+            JobEntry {
+                info: JobInfo {
+                    id: id.clone(),
+                    root,
+                    state: JobState::Running,
+                    started_at: Utc::now().to_rfc3339(),
+                    finished_at: None,
+                    summary: None,
+                    document_ids: None,
+                    error: None,
+                },
+                cancel: cancel.clone(),
+            },
+        );
+        registry.order.push_back(id.clone());
+        Self::trim_history(&mut registry);
+        Ok((id, cancel))
+    }
+
+    /// Marca un trabajo como terminado con el resultado de la ingesta.
+    pub fn finish(&self, id: &str, result: &anyhow::Result<IngestionSummary>) {
+        let mut registry = self.registry.lock().unwrap();
+        let Some(entry) = registry.entries.get_mut(id) else { return };
+
+        entry.info.finished_at = Some(Utc::now().to_rfc3339());
+        match result {
+            Ok(summary) => {
+                entry.info.state = if entry.cancel.load(Ordering::Relaxed) {
+                    JobState::Cancelled
+                } else {
+                    JobState::Completed
+                };
+                entry.info.summary = Some(summary.clone());
+            }
+            Err(err) => {
+                entry.info.state = JobState::Failed;
+                entry.info.error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Anota los `:Document` creados por el trabajo.
+    pub fn set_document_ids(&self, id: &str, document_ids: Vec<String>) {
+        let mut registry = self.registry.lock().unwrap();
+        if let Some(entry) = registry.entries.get_mut(id) {
+            entry.info.document_ids = Some(document_ids);
+        }
+    }
+
+    /// Solicita la cancelación de un trabajo; se detiene antes del siguiente fichero.
+    pub fn cancel(&self, id: &str) -> Option<JobInfo> {
+        let registry = self.registry.lock().unwrap();
+        let entry = registry.entries.get(id)?;
+        if entry.info.state == JobState::Running {
+            entry.cancel.store(true, Ordering::Relaxed);
+        }
+        Some(entry.info.clone())
+    }
+
+    pub fn get(&self, id: &str) -> Option<JobInfo> {
+        let registry = self.registry.lock().unwrap();
+        registry.entries.get(id).map(|entry| entry.info.clone())
+    }
+
+    /// Historial completo, del más reciente al más antiguo.
+    pub fn list(&self) -> Vec<JobInfo> {
+        let registry = self.registry.lock().unwrap();
+        registry
+            .order
+            .iter()
+            .rev()
+            .filter_map(|id| registry.entries.get(id))
+            .map(|entry| entry.info.clone())
+            .collect()
+    }
+
+    fn trim_history(registry: &mut JobRegistry) {
+        while registry.order.len() > MAX_FINISHED_JOBS + 1 {
+            let Some(position) = registry.order.iter().position(|id| {
+                registry
+                    .entries
+                    .get(id)
+                    .is_some_and(|entry| entry.info.state != JobState::Running)
+            }) else {
+                break;
+            };
+            if let Some(id) = registry.order.remove(position) {
+                registry.entries.remove(&id);
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Detección del idioma de documentos, chunks y preguntas con `whatlang`.
+//! Los idiomas se guardan como códigos ISO 639-1 (`es`, `en`...).
+
+use whatlang::Lang;
+
+/// Código para los textos cuyo idioma no se puede determinar (ISO 639-2).
+pub const UNDETERMINED: &str = "und";
+
+/// Caracteres del documento que se analizan; más no cambia el resultado y
+/// sólo cuesta tiempo.
+const DOCUMENT_SAMPLE_CHARS: usize = 20_000;
+
+/// ISO 639-3 (el código de `whatlang`) → ISO 639-1.
+const ISO_639_1: &[(&str, &str)] = &[
+    ("afr", "af"), ("aka", "ak"), ("amh", "am"), ("ara", "ar"), ("aze", "az"), ("bel", "be"),
+    ("ben", "bn"), ("bul", "bg"), ("cat", "ca"), ("ces", "cs"), ("cmn", "zh"), ("dan", "da"),
+    ("deu", "de"), ("ell", "el"), ("eng", "en"), ("epo", "eo"), ("est", "et"), ("fin", "fi"),
+    ("fra", "fr"), ("guj", "gu"), ("heb", "he"), ("hin", "hi"), ("hrv", "hr"), ("hun", "hu"),
+    ("hye", "hy"), ("ind", "id"), ("ita", "it"), ("jav", "jv"), ("jpn", "ja"), ("kan", "kn"),
+    ("kat", "ka"), ("khm", "km"), ("kor", "ko"), ("lat", "la"), ("lav", "lv"), ("lit", "lt"),
+    ("mal", "ml"), ("mar", "mr"), ("mkd", "mk"), ("mya", "my"), ("nep", "ne"), ("nld", "nl"),
+    ("nob", "nb"), ("ori", "or"), ("pan", "pa"), ("pes", "fa"), ("pol", "pl"), ("por", "pt"),
+    ("ron", "ro"), ("rus", "ru"), ("sin", "si"), ("slk", "sk"), ("slv", "sl"), ("sna", "sn"),
+    ("spa", "es"), ("srp", "sr"), ("swe", "sv"), ("tam", "ta"), ("tel", "te"), ("tgl", "tl"),
+    ("tha", "th"), ("tuk", "tk"), ("tur", "tr"), ("ukr", "uk"), ("urd", "ur"), ("uzb", "uz"),
+    ("vie", "vi"), ("yid", "yi"), ("zul", "zu"),
+];
+
+fn to_iso_639_1(lang: Lang) -> &'static str {
+    let code = lang.code();
+    ISO_639_1.iter().find(|(iso3, _)| *iso3 == code).map_or(code, |(_, iso1)| iso1)
+}
+
+/// Idioma de un texto, sólo si la detección es fiable.
+pub fn detect(text: &str) -> Option<&'static str> {
+    whatlang::detect(text).filter(|info| info.is_reliable()).map(|info| to_iso_639_1(info.lang()))
+}
+
+/// Idioma de un documento a partir de su texto extraído, o `und`.
+pub fn detect_document(text: &str) -> String {
+    let sample = match text.char_indices().nth(DOCUMENT_SAMPLE_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+    detect(sample).unwrap_or(UNDETERMINED).to_string()
+}
+
+/// Idioma de un chunk; si es demasiado corto o ambiguo, el del documento.
+pub fn detect_chunk(text: &str, document_language: &str) -> String {
+    detect(text).unwrap_or(document_language).to_string()
+}
+
+/// Normaliza el idioma pedido en un filtro: acepta ISO 639-1 o 639-3
+/// (`es`, `spa`). `None` si no es un idioma conocido.
+pub fn normalize(code: &str) -> Option<&'static str> {
+    let code = code.trim().to_lowercase();
+    ISO_639_1
+        .iter()
+        .find(|(iso3, iso1)| *iso1 == code || *iso3 == code)
+        .map(|(_, iso1)| *iso1)
+}
+
+/// Nombre en inglés de un idioma ISO 639-1, para indicárselo al LLM.
+pub fn english_name(code: &str) -> Option<&'static str> {
+    let (iso3, _) = ISO_639_1.iter().find(|(_, iso1)| *iso1 == code)?;
+    Lang::from_code(*iso3).map(Lang::eng_name)
+}
@@ -1,13 +1,37 @@
 //! Abstracción sobre Rig para trabajar con distintos proveedores de LLM.
-//! De momento se implementa OpenAI; Gemini/Ollama quedan preparados para el futuro.
+//! Implementados OpenAI, Gemini, Ollama y cualquier servidor compatible con la
+//! API de OpenAI (vLLM, llama.cpp...). Embeddings, respuestas y extracción
+//! pueden usar cada uno un proveedor distinto. Con `--offline` (o
+//! `LLM_OFFLINE=true`) los tres usos pasan a un backend simulado y determinista
+//! (ver `llm_backend`).
 
-use crate::config::{AppConfig, LlmProvider};
+use std::sync::Arc;
+
+use crate::config::AppConfig;
+use crate::llm_backend::{FakeBackend, LlmBackend, ProviderBackend};
 use anyhow::{anyhow, Result};
-use rig::completion::Prompt;
-use rig::embeddings::EmbeddingModel; // <- para .embed_texts
 use serde::Deserialize;
 use tracing::warn;
 
+pub(crate) const ANSWER_PROMPT: &str = r#"
+Eres un asistente experto en RAG.
+Respondes en el mismo idioma en que está escrita la pregunta del usuario (aunque el contexto esté en otro), de forma clara y concisa.
+Sólo puedes usar la información suministrada en el contexto. El contexto puede contener texto de documentos y hechos extraídos de un grafo de conocimiento.
+Si el contexto no contiene la respuesta, di explícitamente que no la sabes.
+"#;
+
+pub(crate) const EXTRACTION_PROMPT: &str = r#"
+Tu tarea es analizar el texto y extraer entidades y relaciones para un grafo de conocimiento.
+- Identifica y clasifica entidades en una de estas categorías: 'Person', 'Organization', 'Concept', 'Technology'.
+- Identifica relaciones entre esas entidades como una tripleta (sujeto, predicado, objeto). El predicado debe ser un identificador conciso en mayúsculas (ej: 'IS_A', 'PART_OF', 'CEO_OF').
+
+La salida DEBE ser un único objeto JSON válido con dos claves: "entities" y "relations".
+- "entities": una lista de objetos, cada uno con "id" (nombre de la entidad) y "label".
+- "relations": una lista de objetos, cada uno con "subject", "predicate" y "object".
+
+Si no encuentras nada, devuelve listas vacías. No incluyas explicaciones, solo el JSON.
+"#;
+
 /// Resultado de un embedding de un chunk.
 #[derive(Debug, Clone)]
 pub struct EmbeddedChunk {
@@ -38,22 +62,35 @@ pub struct ExtractionResult {
 }
 
 
-/// Gestor de LLMs y embeddings.
+/// Gestor de LLMs y embeddings: un backend por uso.
 #[derive(Debug, Clone)]
 pub struct LlmManager {
-    pub provider: LlmProvider,
-    pub embedding_model: String,
-    pub chat_model: String,
+    pub embedder: Arc<dyn LlmBackend>,
+    pub answerer: Arc<dyn LlmBackend>,
+    pub extractor: Arc<dyn LlmBackend>,
 }
 
 impl LlmManager {
     /// Construye el manager a partir de la configuración.
     pub fn from_config(cfg: &AppConfig) -> Result<Self> {
-        Ok(Self {
-            provider: cfg.llm_provider.clone(),
-            embedding_model: cfg.llm_embedding_model.clone(),
-            chat_model: cfg.llm_chat_model.clone(),
-        })
+        if cfg.llm_offline {
+            let fake: Arc<dyn LlmBackend> = Arc::new(FakeBackend::from_config(cfg)?);
+            return Ok(Self::with_backends(fake.clone(), fake.clone(), fake));
+        }
+        Ok(Self::with_backends(
+            Arc::new(ProviderBackend::new(cfg.embedding_provider.clone(), &cfg.llm_embedding_model, cfg)),
+            Arc::new(ProviderBackend::new(cfg.answer_provider.clone(), &cfg.llm_answer_model, cfg)),
+            Arc::new(ProviderBackend::new(cfg.extraction_provider.clone(), &cfg.llm_extraction_model, cfg)),
+        ))
+    }
+
+    /// Manager con backends concretos para embeddings, respuestas y extracción.
+    pub fn with_backends(
+        embedder: Arc<dyn LlmBackend>,
+        answerer: Arc<dyn LlmBackend>,
+        extractor: Arc<dyn LlmBackend>,
+    ) -> Self {
+        Self { embedder, answerer, extractor }
     }
 
     // ---------------------------------------------------------------------
@@ -61,47 +98,13 @@ impl LlmManager {
     // ---------------------------------------------------------------------
 
     /// Calcula embeddings para una lista de (id, texto).
-    ///
-    /// Nota: sólo implementado para OpenAI. Para otros proveedores
-    /// se podrían añadir ramas adicionales al `match`.
     pub async fn embed_chunks(
         &self,
         chunks: &[(String, String)],
     ) -> Result<Vec<EmbeddedChunk>> {
-        match self.provider {
-            LlmProvider::OpenAI => self.embed_with_openai(chunks).await,
-            ref other => Err(anyhow!(
-                "Proveedor LLM {:?} aún no implementado para embeddings",
-                other
-            )),
-        }
-    }
-
-    async fn embed_with_openai(
-        &self,
-        chunks: &[(String, String)],
-    ) -> Result<Vec<EmbeddedChunk>> {
-        use rig::providers::openai::{self, TEXT_EMBEDDING_3_SMALL};
-        // Trait para client.embedding_model(...)
-        use rig::client::EmbeddingsClient as _;
-
-        // Cliente OpenAI de Rig
-        let client = openai::Client::from_env();
-
-        // Modelo de embeddings: config o default
-        let model_name = if self.embedding_model.is_empty() {
-            TEXT_EMBEDDING_3_SMALL
-        } else {
-            self.embedding_model.as_str()
-        };
-
-        let embedding_model = client.embedding_model(model_name);
-
         // Extraemos sólo los textos
         let texts: Vec<String> = chunks.iter().map(|(_, text)| text.clone()).collect();
-
-        // Embeddings en bloque (.embed_texts viene de EmbeddingModel)
-        let embeddings = embedding_model.embed_texts(texts.clone()).await?;
+        let embeddings = self.embedder.embed(texts).await?;
 
         if embeddings.len() != chunks.len() {
             return Err(anyhow!(
@@ -113,118 +116,74 @@ impl LlmManager {
 
         // Reconstruimos EmbeddedChunk con id + texto + vector
         let mut result = Vec::new();
-        for ((id, text), emb) in chunks.iter().zip(embeddings.iter()) {
+        for ((id, text), vector) in chunks.iter().zip(embeddings) {
             result.push(EmbeddedChunk {
                 id: id.clone(),
                 text: text.clone(),
-                vector: emb.vec.clone(),
+                vector,
             });
         }
 
         Ok(result)
     }
 
+    /// Embedding de una consulta, con el mismo modelo que los chunks.
+    pub async fn embed_query(&self, text: &str) -> Result<Vec<f64>> {
+        self.embedder
+            .embed(vec![text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("No se pudo generar embedding de la query"))
+    }
+
     // ---------------------------------------------------------------------
     // CHAT / COMPLETION
     // ---------------------------------------------------------------------
 
     /// Genera una respuesta a partir de una pregunta y un contexto
-    /// (concatenación de chunks relevantes).
+    /// (concatenación de chunks relevantes). `language` es el nombre en
+    /// inglés del idioma de la pregunta, si se ha detectado.
     pub async fn answer_with_context(
         &self,
         question: &str,
         context: &str,
+        language: Option<&str>,
     ) -> Result<String> {
-        match self.provider {
-            LlmProvider::OpenAI => self.answer_with_openai(question, context).await,
-            ref other => Err(anyhow!(
-                "Proveedor LLM {:?} aún no implementado para chat",
-                other
-            )),
-        }
-    }
-
-    async fn answer_with_openai(
-        &self,
-        question: &str,
-        context: &str,
-    ) -> Result<String> {
-        use rig::providers::openai;
-        // Trait para client.agent(...)
-        use rig::client::CompletionClient as _;
-
-        const SYSTEM_PROMPT: &str = r#"
-Eres un asistente experto en RAG.
-Respondes en español, de forma clara y concisa.
-Sólo puedes usar la información suministrada en el contexto. El contexto puede contener texto de documentos y hechos extraídos de un grafo de conocimiento.
-Si el contexto no contiene la respuesta, di explícitamente que no la sabes.
-"#;
-
-        let client = openai::Client::from_env();
-
-        // Modelo de chat por defecto si no se ha configurado otro
-        let model_name = if self.chat_model.is_empty() {
-            "gpt-4o-mini"
-        } else {
-            self.chat_model.as_str()
-        };
-
-        let full_context = format!(
+        let mut full_context = format!(
             "Contexto:\n{}\n\nPregunta del usuario:\n{}",
             context, question
         );
+        if let Some(language) = language {
+            full_context.push_str(&format!("\n\nIdioma de la pregunta (y de la respuesta): {}", language));
+        }
 
-        let agent = client
-            .agent(model_name)
-            .preamble(SYSTEM_PROMPT)
-            .context(&full_context)
-            .build();
-
-        let answer = agent.prompt(question).await?;
-        Ok(answer)
+        // El contexto va en el propio mensaje: no todos los proveedores de Rig
+        // envían los documentos estáticos del agente (Gemini los descarta).
+        self.answerer.complete(ANSWER_PROMPT, &full_context).await
     }
 
     // --- MEJORA: Extracción de Entidades y Relaciones ---
     
     pub async fn extract_entities_and_relations(&self, text: &str) -> Result<ExtractionResult> {
-        use rig::providers::openai;
-        use rig::client::CompletionClient as _;
-
-        const EXTRACTION_PROMPT: &str = r#"
-Tu tarea es analizar el texto y extraer entidades y relaciones para un grafo de conocimiento.
-- Identifica y clasifica entidades en una de estas categorías: 'Person', 'Organization', 'Concept', 'Technology'.
-- Identifica relaciones entre esas entidades como una tripleta (sujeto, predicado, objeto). El predicado debe ser un identificador conciso en mayúsculas (ej: 'IS_A', 'PART_OF', 'CEO_OF').
-
-La salida DEBE ser un único objeto JSON válido con dos claves: "entities" y "relations".
-- "entities": una lista de objetos, cada uno con "id" (nombre de la entidad) y "label".
-- "relations": una lista de objetos, cada uno con "subject", "predicate" y "object".
-
-Si no encuentras nada, devuelve listas vacías. No incluyas explicaciones, solo el JSON.
-"#;
-        let client = openai::Client::from_env();
-        let model_name = if self.chat_model.is_empty() { "gpt-4o-mini" } else { self.chat_model.as_str() };
-
-        let agent = client
-            .agent(model_name)
-            .preamble(EXTRACTION_PROMPT)
-            .build();
+        self.extractor.extract(text).await
+    }
+}
 
-        let response = agent.prompt(text).await?;
+/// Interpreta la respuesta del modelo a `EXTRACTION_PROMPT`.
+pub(crate) fn parse_extraction(response: &str) -> ExtractionResult {
+    // Limpiar la respuesta del LLM para asegurar que solo contenga el JSON
+    let json_response = response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_end_matches("```")
+        .trim();
         
-        // Limpiar la respuesta del LLM para asegurar que solo contenga el JSON
-        let json_response = response
-            .trim()
-            .trim_start_matches("```json")
-            .trim_end_matches("```")
-            .trim();
-            
-        match serde_json::from_str::<ExtractionResult>(json_response) {
-            Ok(result) => Ok(result),
-            Err(e) => {
-                warn!("No se pudo parsear el JSON de extracción de entidades para un chunk. Error: {}. Respuesta LLM: '{}'", e, response);
-                // Devolvemos un resultado vacío en caso de error para no detener la ingesta.
-                Ok(ExtractionResult::default())
-            }
+    match serde_json::from_str::<ExtractionResult>(json_response) {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("No se pudo parsear el JSON de extracción de entidades para un chunk. Error: {}. Respuesta LLM: '{}'", e, response);
+            // Devolvemos un resultado vacío en caso de error para no detener la ingesta.
+            ExtractionResult::default()
         }
     }
 }
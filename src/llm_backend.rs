@@ -0,0 +1,433 @@
+//! Backends de LLM: lo que `LlmManager` necesita de un proveedor (embeddings,
+//! completado y extracción), implementado sobre Rig para los proveedores reales
+//! y de forma determinista y sin red para el modo `--offline`.
+
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use rig::agent::AgentBuilder;
+use rig::client::{CompletionClient as _, EmbeddingsClient as _};
+use rig::completion::{CompletionModel, Prompt};
+use rig::embeddings::EmbeddingModel; // <- para .embed_texts
+use rig::providers::gemini::completion::gemini_api_types::AdditionalParameters;
+use rig::providers::{gemini, ollama, openai};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::{AppConfig, LlmProvider};
+use crate::llm::{self, ExtractionResult};
+
+/// Textos por petición de embeddings en Gemini (`batchEmbedContents` admite 100).
+const GEMINI_EMBEDDING_BATCH: usize = 100;
+
+/// Textos por petición de embeddings en OpenAI: la API admite 2048 textos y
+/// 300 000 tokens por petición, así que cabe un CSV grande troceado por filas.
+const OPENAI_EMBEDDING_BATCH: usize = 512;
+
+/// Textos por petición en Ollama y servidores compatibles con OpenAI, que no
+/// publican un límite: lotes moderados para no agotar su memoria.
+const LOCAL_EMBEDDING_BATCH: usize = 128;
+
+/// Caracteres del contexto que repite la respuesta simulada.
+const FAKE_ANSWER_CHARS: usize = 800;
+
+/// Un proveedor de LLM con un modelo concreto.
+#[async_trait]
+pub trait LlmBackend: Debug + Send + Sync {
+    /// Embeddings de `texts`, en el mismo orden.
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>>;
+
+    /// Respuesta del modelo a `prompt` con las instrucciones `preamble`.
+    async fn complete(&self, preamble: &str, prompt: &str) -> Result<String>;
+
+    /// Entidades y relaciones de un texto.
+    async fn extract(&self, text: &str) -> Result<ExtractionResult>;
+
+    /// Proveedor y modelo, para los registros.
+    fn describe(&self) -> String;
+}
+
+/// Backend sobre un proveedor real a través de Rig.
+#[derive(Debug, Clone)]
+pub struct ProviderBackend {
+    provider: LlmProvider,
+    /// Modelo de embeddings o de chat, según el uso del backend.
+    model: String,
+    /// Dimensiones pedidas al modelo de embeddings, si el proveedor lo permite.
+    embedding_dimensions: usize,
+    ollama_base_url: String,
+    openai_compatible_base_url: String,
+    openai_compatible_api_key: String,
+}
+
+impl ProviderBackend {
+    pub fn new(provider: LlmProvider, model: &str, cfg: &AppConfig) -> Self {
+        Self {
+            provider,
+            model: model.to_string(),
+            embedding_dimensions: cfg.llm_embedding_dimensions,
+            ollama_base_url: cfg.ollama_base_url.clone(),
+            openai_compatible_base_url: cfg.openai_compatible_base_url.clone(),
+            openai_compatible_api_key: cfg.openai_compatible_api_key.clone(),
+        }
+    }
+
+    fn gemini_client(&self) -> Result<gemini::Client> {
+        let api_key = std::env::var("GEMINI_API_KEY").map_err(|_| anyhow!("Falta GEMINI_API_KEY en el entorno"))?;
+        Ok(gemini::Client::builder(&api_key).build()?)
+    }
+
+    fn ollama_client(&self) -> ollama::Client {
+        ollama::Client::builder().base_url(&self.ollama_base_url).build()
+    }
+
+    fn openai_compatible_client(&self) -> openai::Client {
+        openai::Client::builder(&self.openai_compatible_api_key)
+            .base_url(&self.openai_compatible_base_url)
+            .build()
+    }
+}
+
+#[async_trait]
+impl LlmBackend for ProviderBackend {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        match self.provider {
+            LlmProvider::OpenAI => {
+                let model = openai::Client::from_env().embedding_model(&self.model);
+                embed_with(model, texts, OPENAI_EMBEDDING_BATCH).await
+            }
+            LlmProvider::Gemini => {
+                let model = self.gemini_client()?.embedding_model_with_ndims(&self.model, self.embedding_dimensions);
+                embed_with(model, texts, GEMINI_EMBEDDING_BATCH).await
+            }
+            LlmProvider::Ollama => {
+                let model = self.ollama_client().embedding_model(&self.model);
+                embed_with(model, texts, LOCAL_EMBEDDING_BATCH).await
+            }
+            LlmProvider::OpenAICompatible => {
+                let model = self.openai_compatible_client().embedding_model(&self.model);
+                embed_with(model, texts, LOCAL_EMBEDDING_BATCH).await
+            }
+        }
+    }
+
+    async fn complete(&self, preamble: &str, prompt: &str) -> Result<String> {
+        match self.provider {
+            LlmProvider::OpenAI => prompt_with(openai::Client::from_env().agent(&self.model), preamble, prompt).await,
+            LlmProvider::Gemini => {
+                // Rig no acepta peticiones a Gemini sin `generationConfig`.
+                let params = serde_json::to_value(AdditionalParameters::default())?;
+                let agent = self.gemini_client()?.agent(&self.model).additional_params(params);
+                prompt_with(agent, preamble, prompt).await
+            }
+            LlmProvider::Ollama => prompt_with(self.ollama_client().agent(&self.model), preamble, prompt).await,
+            LlmProvider::OpenAICompatible => {
+                // Estos servidores implementan Chat Completions, no la API Responses.
+                let model = self.openai_compatible_client().completion_model(&self.model).completions_api();
+                prompt_with(AgentBuilder::new(model), preamble, prompt).await
+            }
+        }
+    }
+
+    async fn extract(&self, text: &str) -> Result<ExtractionResult> {
+        let response = self.complete(llm::EXTRACTION_PROMPT, text).await?;
+        Ok(llm::parse_extraction(&response))
+    }
+
+    fn describe(&self) -> String {
+        format!("{:?} ({})", self.provider, self.model)
+    }
+}
+
+/// Embeddings con cualquier modelo de Rig, en peticiones de hasta `batch` textos.
+async fn embed_with<M: EmbeddingModel>(model: M, texts: Vec<String>, batch: usize) -> Result<Vec<Vec<f64>>> {
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(batch) {
+        let embeddings = model.embed_texts(batch.to_vec()).await?;
+        vectors.extend(embeddings.into_iter().map(|embedding| embedding.vec));
+    }
+    Ok(vectors)
+}
+
+/// Construye el agente con sus instrucciones y le pasa el prompt.
+async fn prompt_with<M: CompletionModel>(agent: AgentBuilder<M>, preamble: &str, prompt: &str) -> Result<String> {
+    Ok(agent.preamble(preamble).build().prompt(prompt).await?)
+}
+
+/// Extracción guionizada: se devuelve cuando el texto contiene `pattern`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptedExtraction {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(flatten)]
+    pub result: ExtractionResult,
+}
+
+/// Respuesta guionizada: se devuelve cuando el prompt contiene `pattern`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptedCompletion {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    pub response: String,
+}
+
+/// Guion del backend simulado (fichero `LLM_OFFLINE_SCRIPT`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FakeScript {
+    pub extractions: Vec<ScriptedExtraction>,
+    pub completions: Vec<ScriptedCompletion>,
+}
+
+/// Backend determinista que no sale de la máquina. Los embeddings son un
+/// hashing de las palabras del texto (textos con palabras en común quedan
+/// cerca); las respuestas y extracciones salen del guion o, si nada casa,
+/// repiten el contexto y no extraen nada.
+#[derive(Debug, Clone)]
+pub struct FakeBackend {
+    dimensions: usize,
+    script: FakeScript,
+}
+
+impl FakeBackend {
+    pub fn new(dimensions: usize, script: FakeScript) -> Self {
+        Self { dimensions: dimensions.max(1), script }
+    }
+
+    /// Backend con las dimensiones de la configuración y el guion de
+    /// `LLM_OFFLINE_SCRIPT`, si lo hay.
+    pub fn from_config(cfg: &AppConfig) -> Result<Self> {
+        let script = if cfg.llm_offline_script.is_empty() {
+            FakeScript::default()
+        } else {
+            let raw = std::fs::read_to_string(&cfg.llm_offline_script)
+                .with_context(|| format!("No se pudo leer el guion {}", cfg.llm_offline_script))?;
+            serde_json::from_str(&raw)
+                .with_context(|| format!("Guion inválido en {}", cfg.llm_offline_script))?
+        };
+        Ok(Self::new(cfg.llm_embedding_dimensions, script))
+    }
+
+    fn hash_embedding(&self, text: &str) -> Vec<f64> {
+        let mut vector = vec![0.0; self.dimensions];
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+            let digest = Sha256::digest(word.to_lowercase().as_bytes());
+            let mut bucket = [0u8; 8];
+            bucket.copy_from_slice(&digest[..8]);
+            let bucket = (u64::from_le_bytes(bucket) % self.dimensions as u64) as usize;
+            vector[bucket] += if digest[8] & 1 == 0 { 1.0 } else { -1.0 };
+        }
+
+        let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm == 0.0 {
+            // El índice coseno no admite vectores nulos.
+            vector[0] = 1.0;
+        } else {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+
+#[async_trait]
+impl LlmBackend for FakeBackend {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        Ok(texts.iter().map(|text| self.hash_embedding(text)).collect())
+    }
+
+    async fn complete(&self, _preamble: &str, prompt: &str) -> Result<String> {
+        if let Some(scripted) = self.script.completions.iter().find(|c| prompt.contains(&c.pattern)) {
+            return Ok(scripted.response.clone());
+        }
+        let echoed: String = prompt.chars().take(FAKE_ANSWER_CHARS).collect();
+        Ok(format!("Respuesta simulada (modo sin conexión).\n\n{}", echoed))
+    }
+
+    async fn extract(&self, text: &str) -> Result<ExtractionResult> {
+        Ok(self
+            .script
+            .extractions
+            .iter()
+            .find(|e| text.contains(&e.pattern))
+            .map(|e| e.result.clone())
+            .unwrap_or_default())
+    }
+
+    fn describe(&self) -> String {
+        format!("simulado ({} dimensiones)", self.dimensions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::llm::LlmManager;
+
+    /// Servidor local que imita `/api/embed` y `/api/chat` de Ollama: el
+    /// embedding de cada texto es `[posición, longitud]`, la extracción devuelve
+    /// una entidad fija y el resto de peticiones repite el mensaje del usuario.
+    async fn ollama_stub() -> String {
+        async fn embed(Json(request): Json<Value>) -> Json<Value> {
+            let inputs = request["input"].as_array().cloned().unwrap_or_default();
+            let embeddings: Vec<Vec<f64>> = inputs
+                .iter()
+                .enumerate()
+                .map(|(i, text)| vec![i as f64, text.as_str().unwrap_or_default().len() as f64])
+                .collect();
+            Json(json!({ "model": request["model"], "embeddings": embeddings }))
+        }
+
+        async fn chat(Json(request): Json<Value>) -> Json<Value> {
+            let messages = request["messages"].as_array().cloned().unwrap_or_default();
+            let system = messages.iter().find(|m| m["role"] == "system").map(|m| m["content"].to_string());
+            let user = messages.iter().rev().find(|m| m["role"] == "user").map(|m| m["content"].clone());
+            let content = if system.is_some_and(|s| s.contains("extraer entidades")) {
+                json!({
+                    "entities": [{ "id": "Neo4j", "label": "Technology" }],
+                    "relations": [{ "subject": "NexusRAG", "predicate": "USES", "object": "Neo4j" }],
+                })
+                .to_string()
+            } else {
+                format!("eco: {}", user.and_then(|u| u.as_str().map(str::to_string)).unwrap_or_default())
+            };
+            Json(json!({
+                "model": request["model"],
+                "created_at": "2024-01-01T00:00:00Z",
+                "message": { "role": "assistant", "content": content },
+                "done": true,
+            }))
+        }
+
+        let app = Router::new().route("/api/embed", post(embed)).route("/api/chat", post(chat));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", address)
+    }
+
+    fn ollama_backend(base_url: &str, model: &str) -> Arc<dyn LlmBackend> {
+        Arc::new(ProviderBackend {
+            provider: LlmProvider::Ollama,
+            model: model.to_string(),
+            embedding_dimensions: 2,
+            ollama_base_url: base_url.to_string(),
+            openai_compatible_base_url: String::new(),
+            openai_compatible_api_key: String::new(),
+        })
+    }
+
+    fn scripted_backend() -> FakeBackend {
+        let script: FakeScript = serde_json::from_value(json!({
+            "extractions": [{
+                "match": "Neo4j",
+                "entities": [{ "id": "Neo4j", "label": "Technology" }],
+                "relations": [{ "subject": "NexusRAG", "predicate": "USES", "object": "Neo4j" }],
+            }],
+            "completions": [{ "match": "grafo", "response": "Un grafo de conocimiento." }],
+        }))
+        .unwrap();
+        FakeBackend::new(16, script)
+    }
+
+    #[test]
+    fn hash_embedding_is_deterministic_and_unit_norm() {
+        let backend = FakeBackend::new(16, FakeScript::default());
+        let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+        let first = backend.hash_embedding("Neo4j guarda el grafo");
+        assert_eq!(first, backend.hash_embedding("neo4j, guarda el GRAFO"));
+        assert_eq!(first.len(), 16);
+        assert!((norm(&first) - 1.0).abs() < 1e-9);
+
+        // Sin palabras no hay cubetas, pero el índice coseno no admite el vector nulo.
+        for text in ["", "  ¿?¡! ", "---"] {
+            let vector = backend.hash_embedding(text);
+            assert!(vector.iter().any(|x| *x != 0.0), "{text:?}");
+            assert!((norm(&vector) - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[tokio::test]
+    async fn fake_backend_follows_the_script() {
+        let backend = scripted_backend();
+
+        let extraction = backend.extract("NexusRAG guarda los chunks en Neo4j.").await.unwrap();
+        assert_eq!(extraction.entities[0].id, "Neo4j");
+        assert_eq!(extraction.relations[0].predicate, "USES");
+        assert!(backend.extract("Texto sin coincidencias.").await.unwrap().entities.is_empty());
+
+        let answer = backend.complete("", "¿Qué es un grafo?").await.unwrap();
+        assert_eq!(answer, "Un grafo de conocimiento.");
+    }
+
+    #[tokio::test]
+    async fn fake_backend_echoes_unscripted_prompts() {
+        let backend = FakeBackend::new(16, FakeScript::default());
+
+        let answer = backend.complete("", "Pregunta sin guion").await.unwrap();
+        assert_eq!(answer, "Respuesta simulada (modo sin conexión).\n\nPregunta sin guion");
+
+        let long_prompt = "ñ".repeat(FAKE_ANSWER_CHARS + 50);
+        let answer = backend.complete("", &long_prompt).await.unwrap();
+        assert!(answer.ends_with(&"ñ".repeat(FAKE_ANSWER_CHARS)));
+        assert!(!answer.ends_with(&"ñ".repeat(FAKE_ANSWER_CHARS + 1)));
+    }
+
+    #[tokio::test]
+    async fn manager_runs_offline_end_to_end() {
+        let fake: Arc<dyn LlmBackend> = Arc::new(scripted_backend());
+        let manager = LlmManager::with_backends(fake.clone(), fake.clone(), fake);
+
+        let chunks = vec![
+            ("a".to_string(), "NexusRAG usa Neo4j".to_string()),
+            ("b".to_string(), "El grafo de conocimiento".to_string()),
+        ];
+        let embedded = manager.embed_chunks(&chunks).await.unwrap();
+        assert_eq!(embedded.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(embedded[0].vector, manager.embed_query("NexusRAG usa Neo4j").await.unwrap());
+        assert_ne!(embedded[0].vector, embedded[1].vector);
+
+        let extraction = manager.extract_entities_and_relations(&chunks[0].1).await.unwrap();
+        assert_eq!(extraction.entities[0].label, "Technology");
+
+        let answer = manager.answer_with_context("¿Qué es el grafo?", &chunks[1].1, Some("Spanish")).await.unwrap();
+        assert_eq!(answer, "Un grafo de conocimiento.");
+        let echoed = manager.answer_with_context("¿Y Neo4j?", &chunks[0].1, None).await.unwrap();
+        assert!(echoed.contains("Contexto:\nNexusRAG usa Neo4j"), "{echoed}");
+    }
+
+    #[tokio::test]
+    async fn ollama_round_trip() {
+        let base_url = ollama_stub().await;
+        let manager = LlmManager::with_backends(
+            ollama_backend(&base_url, "embedder"),
+            ollama_backend(&base_url, "answerer"),
+            ollama_backend(&base_url, "extractor"),
+        );
+
+        // Más textos que un lote, para comprobar que se trocean sin perder el orden.
+        let chunks: Vec<(String, String)> =
+            (0..LOCAL_EMBEDDING_BATCH + 3).map(|i| (format!("c{i}"), "x".repeat(i + 1))).collect();
+        let embedded = manager.embed_chunks(&chunks).await.unwrap();
+        assert_eq!(embedded.len(), chunks.len());
+        for (i, chunk) in embedded.iter().enumerate() {
+            assert_eq!(chunk.id, format!("c{i}"));
+            assert_eq!(chunk.vector, vec![(i % LOCAL_EMBEDDING_BATCH) as f64, (i + 1) as f64]);
+        }
+
+        let answer = manager.answer_with_context("¿Qué usa NexusRAG?", "NexusRAG usa Neo4j.", Some("Spanish")).await.unwrap();
+        assert!(answer.starts_with("eco: Contexto:\nNexusRAG usa Neo4j."), "{answer}");
+        assert!(answer.contains("¿Qué usa NexusRAG?"), "{answer}");
+
+        let extraction = manager.extract_entities_and_relations("NexusRAG usa Neo4j.").await.unwrap();
+        assert_eq!(extraction.entities.len(), 1);
+        assert_eq!(extraction.entities[0].id, "Neo4j");
+        assert_eq!(extraction.relations[0].predicate, "USES");
+    }
+}
@@ -1,18 +1,31 @@
 // Módulos de la aplicación
 mod api;
 mod app_state;
+mod archive;
+mod chunker;
 mod config;
+mod extract;
+mod file_filter;
+mod git;
 mod ingest;
+mod jobs;
+mod language;
 mod llm;
+mod llm_backend;
 mod models;
 mod neo4j_client;
+mod pii;
+mod provenance;
 mod rag;
+mod secrets;
+mod structured;
 mod vector_store;
+mod watcher;
 
 use crate::app_state::{AppState, Status};
 use axum::Router;
 use std::sync::{Arc, Mutex};
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, Semaphore};
 use tower_http::{
     cors::{Any, CorsLayer},
     services::ServeDir,
@@ -29,7 +42,10 @@ async fn main() {
         .init();
 
     // 2. Cargar configuración
-    let cfg = config::AppConfig::from_env().expect("Error al cargar la configuración");
+    let mut cfg = config::AppConfig::from_env().expect("Error al cargar la configuración");
+    if std::env::args().any(|arg| arg == "--offline") {
+        cfg.llm_offline = true;
+    }
 
     // 3. Conectar a Neo4j y asegurar esquemas
     let graph = neo4j_client::connect_from_config(&cfg)
@@ -44,6 +60,12 @@ async fn main() {
 
     // 4. Inicializar gestor de LLMs
     let llm_manager = llm::LlmManager::from_config(&cfg).expect("Error inicializando LLM Manager");
+    info!(
+        "LLM — embeddings: {}, respuestas: {}, extracción: {}",
+        llm_manager.embedder.describe(),
+        llm_manager.answerer.describe(),
+        llm_manager.extractor.describe()
+    );
 
     // Crear canal para la señal de apagado.
     let (shutdown_tx, shutdown_rx) = oneshot::channel();
@@ -53,12 +75,16 @@ async fn main() {
         config: cfg.clone(),
         graph: Arc::new(graph),
         llm_manager,
+        llm_permits: Arc::new(Semaphore::new(cfg.ingest_max_llm_requests)),
         status: Arc::new(Mutex::new(Status {
             is_busy: false,
             message: "Servidor listo.".to_string(),
             progress: 0.0, // MODIFICADO AQUÍ: Añadido el campo faltante.
+            is_watching: false,
         })),
         current_dir: Arc::new(Mutex::new(None)),
+        watcher: Arc::new(Mutex::new(None)),
+        jobs: jobs::JobManager::default(),
         shutdown_sender: Arc::new(Mutex::new(Some(shutdown_tx))),
     };
 
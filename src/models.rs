@@ -13,6 +13,10 @@ pub struct FileNode {
     pub size_bytes: i64,
     pub modified_at: String,
     pub mime_type: Option<String>,
+    /// Hash SHA-256 (hex) del contenido, usado para la ingesta incremental.
+    pub content_hash: String,
+    /// Proveedor, modelo y dimensiones con que se calcularon sus embeddings.
+    pub embedding_signature: String,
 }
 
 /// Representa un nodo (:Document) en Neo4j.
@@ -21,6 +25,7 @@ pub struct FileNode {
 pub struct DocumentNode {
     pub id: String,
     pub title: String,
+    pub author: Option<String>,
     pub doc_type: String,
     pub language: String,
     pub source: String,
@@ -34,9 +39,39 @@ pub struct ChunkNode {
     pub document_id: String,
     pub index: i64,
     pub text: String,
+    /// Texto con los datos personales sustituidos por marcadores, cuando
+    /// `text` conserva los originales. Es el que se envía al LLM.
+    pub redacted_text: Option<String>,
     pub embedding: Vec<f64>,
     pub tokens: i64,
-    // pub section: Option<String>, // <-- LÍNEA ELIMINADA
+    /// Ruta de encabezados (Markdown) o elemento de código del que procede.
+    pub section: Option<String>,
+    /// Idioma (ISO 639-1) del chunk, o el del documento si no se pudo determinar.
+    pub language: String,
+    /// Primera y última página del original (PDF) que cubre el chunk.
+    pub page_start: Option<i64>,
+    pub page_end: Option<i64>,
+    /// Posición del chunk en el texto extraído del fichero.
+    pub offsets: Option<SourceOffsets>,
+}
+
+impl ChunkNode {
+    /// Texto que puede salir de la máquina (embeddings, extracción, contexto RAG).
+    pub fn model_text(&self) -> &str {
+        self.redacted_text.as_deref().unwrap_or(&self.text)
+    }
+}
+
+/// Posición de un chunk en el texto de origen: rangos semiabiertos de bytes y
+/// caracteres y, en ficheros de texto, líneas (1-based, inclusivas).
+#[derive(Debug, Clone, Copy)]
+pub struct SourceOffsets {
+    pub byte_start: i64,
+    pub byte_end: i64,
+    pub char_start: i64,
+    pub char_end: i64,
+    pub line_start: Option<i64>,
+    pub line_end: Option<i64>,
 }
 
 /// Representa un nodo (:Query) para registrar las consultas RAG realizadas.
@@ -48,6 +83,7 @@ pub struct QueryNode {
 }
 
 /// MEJORA: Representa un nodo de entidad (:Entity) extraído del texto.
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct EntityNode {
     pub id: String,   // ej: "Ley de Moore"
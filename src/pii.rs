@@ -0,0 +1,361 @@
+//! Redacción de datos personales (correos, teléfonos, documentos de identidad)
+//! antes de enviar texto al proveedor de embeddings o al LLM.
+//!
+//! Cada valor se sustituye por un marcador estable (`[EMAIL_3]`): el mismo
+//! valor recibe siempre el mismo marcador en todo el corpus, de modo que las
+//! entidades extraídas siguen enlazando. La correspondencia marcador → valor
+//! se guarda sólo en local, en un fichero JSON.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::config::{AppConfig, PiiKind};
+
+impl PiiKind {
+    /// Prefijo del marcador (`[EMAIL_1]`, `[TELEFONO_1]`, `[ID_NACIONAL_1]`).
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Email => "EMAIL",
+            Self::Phone => "TELEFONO",
+            Self::NationalId => "ID_NACIONAL",
+        }
+    }
+
+    /// Forma canónica del valor, para que `Ana@X.com` y `ana@x.com` compartan marcador.
+    fn normalize(self, value: &str) -> String {
+        match self {
+            Self::Email => value.to_lowercase(),
+            Self::Phone => value.chars().filter(|c| c.is_ascii_digit() || *c == '+').collect(),
+            Self::NationalId => value.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_uppercase(),
+        }
+    }
+}
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b").expect("patrón de email válido")
+});
+
+/// Números con forma de teléfono: con prefijo internacional (`+34 600 123 456`,
+/// `+1 (555) 123-4567`), con prefijo entre paréntesis, en formato
+/// estadounidense (`555-123-4567`) o en los formatos españoles de nueve cifras
+/// (`600123456`, `600 12 34 56`, `91 123 45 67`). Los grupos de cifras sin esa
+/// forma (años, filas de una tabla) no cuentan.
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\+\d{1,3}(?:[\s.-]?(?:\(\d{1,4}\)|\d{1,4})){2,6}\b|\(\d{2,4}\)[\s-]?\d{3,4}[\s-]?\d{3,4}\b|\b\d{3}-\d{3}-\d{4}\b|\b[6789]\d{2}(?:[\s-]?\d{3}){2}\b|\b[6789]\d{2}(?:[\s-]\d{2}){3}\b|\b[89]\d[\s-]\d{3}(?:[\s-]\d{2}){2}\b",
+    )
+    .expect("patrón de teléfono válido")
+});
+
+/// DNI y NIE españoles (se valida la letra de control) y SSN estadounidense.
+static NATIONAL_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:\d{8}[\s-]?[A-Z]|[XYZ][\s-]?\d{7}[\s-]?[A-Z]|\d{3}-\d{2}-\d{4})\b")
+        .expect("patrón de documento de identidad válido")
+});
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[(?:EMAIL|TELEFONO|ID_NACIONAL)_\d+\]").expect("patrón de marcador válido"));
+
+/// Un dato personal localizado en un texto.
+#[derive(Debug, Clone)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    pub range: Range<usize>,
+}
+
+/// Busca datos personales de los tipos indicados. Los rangos devueltos están
+/// ordenados y no se solapan.
+pub fn scan(text: &str, kinds: &[PiiKind]) -> Vec<PiiMatch> {
+    let mut matches = Vec::new();
+    // Los documentos de identidad van primero: un SSN también parece un teléfono.
+    for kind in [PiiKind::NationalId, PiiKind::Email, PiiKind::Phone] {
+        if !kinds.contains(&kind) {
+            continue;
+        }
+        let (pattern, valid): (&Regex, fn(&str) -> bool) = match kind {
+            PiiKind::NationalId => (&NATIONAL_ID, valid_national_id),
+            PiiKind::Email => (&EMAIL, |_| true),
+            PiiKind::Phone => (&PHONE, valid_phone),
+        };
+        matches.extend(
+            pattern
+                .find_iter(text)
+                .filter(|m| valid(m.as_str()) && (kind == PiiKind::Email || standalone(text, m.range())))
+                .map(|m| PiiMatch { kind, range: m.range() }),
+        );
+    }
+
+    // Ordenación estable: ante el mismo rango prevalece el tipo detectado antes.
+    matches.sort_by_key(|m| (m.range.start, std::cmp::Reverse(m.range.end)));
+    let mut merged: Vec<PiiMatch> = Vec::new();
+    for m in matches {
+        match merged.last() {
+            Some(last) if m.range.start < last.range.end => {}
+            _ => merged.push(m),
+        }
+    }
+    merged
+}
+
+/// Descarta números que siguen con `-`/`.` y más cifras (ISBN, referencias...).
+fn standalone(text: &str, range: Range<usize>) -> bool {
+    let continues = |mut chars: std::str::Chars, rev: bool| {
+        let (sep, next) = if rev { (chars.next_back(), chars.next_back()) } else { (chars.next(), chars.next()) };
+        matches!(sep, Some('-' | '.' | '/')) && next.is_some_and(|c| c.is_ascii_digit())
+    };
+    !continues(text[range.end..].chars(), false) && !continues(text[..range.start].chars(), true)
+}
+
+fn valid_phone(value: &str) -> bool {
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    // Una fecha con hora (`2024-05-17 10`) también son grupos de cifras.
+    let looks_like_date = value.len() >= 10
+        && value.as_bytes()[..10].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        });
+    (9..=15).contains(&digits) && !looks_like_date
+}
+
+fn valid_national_id(value: &str) -> bool {
+    const DNI_LETTERS: &[u8] = b"TRWAGMYFPDXBNJZSQVHLCKE";
+    let compact: String = value.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_uppercase();
+    if value.contains('-') && compact.len() == 9 && compact.chars().all(|c| c.is_ascii_digit()) {
+        // SSN: ni el área 000, 666 o 9xx, ni grupo o serie a cero.
+        let (area, rest) = compact.split_at(3);
+        let (group, serial) = rest.split_at(2);
+        return area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000";
+    }
+    let (number, letter) = compact.split_at(compact.len() - 1);
+    let number = match number.as_bytes().first() {
+        Some(b'X') => format!("0{}", &number[1..]),
+        Some(b'Y') => format!("1{}", &number[1..]),
+        Some(b'Z') => format!("2{}", &number[1..]),
+        _ => number.to_string(),
+    };
+    match number.parse::<usize>() {
+        Ok(n) => letter.as_bytes()[0] == DNI_LETTERS[n % 23],
+        Err(_) => false,
+    }
+}
+
+/// Correspondencia local marcador ↔ valor original, persistida en JSON.
+#[derive(Debug)]
+struct PiiVault {
+    path: PathBuf,
+    /// Marcador → valor original (tal como apareció la primera vez).
+    values: BTreeMap<String, String>,
+    /// (tipo, valor normalizado) → marcador.
+    placeholders: HashMap<(PiiKind, String), String>,
+    /// Último número asignado por prefijo.
+    counters: HashMap<&'static str, usize>,
+    dirty: bool,
+}
+
+impl PiiVault {
+    fn load(path: &Path) -> Result<Self> {
+        let values: BTreeMap<String, String> = match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow!("Fichero de datos personales inválido ({}): {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut placeholders = HashMap::new();
+        let mut counters = HashMap::new();
+        for (placeholder, value) in &values {
+            let inner = placeholder.trim_start_matches('[').trim_end_matches(']');
+            let Some((prefix, number)) = inner.rsplit_once('_') else { continue };
+            let Some(kind) = [PiiKind::Email, PiiKind::Phone, PiiKind::NationalId]
+                .into_iter()
+                .find(|k| k.prefix() == prefix)
+            else {
+                continue;
+            };
+            let counter = counters.entry(kind.prefix()).or_insert(0);
+            *counter = (*counter).max(number.parse().unwrap_or(0));
+            placeholders.insert((kind, kind.normalize(value)), placeholder.clone());
+        }
+
+        Ok(Self { path: path.to_path_buf(), values, placeholders, counters, dirty: false })
+    }
+
+    fn placeholder(&mut self, kind: PiiKind, value: &str) -> String {
+        let key = (kind, kind.normalize(value));
+        if let Some(placeholder) = self.placeholders.get(&key) {
+            return placeholder.clone();
+        }
+        let counter = self.counters.entry(kind.prefix()).or_insert(0);
+        *counter += 1;
+        let placeholder = format!("[{}_{}]", kind.prefix(), counter);
+        self.values.insert(placeholder.clone(), value.to_string());
+        self.placeholders.insert(key, placeholder.clone());
+        self.dirty = true;
+        placeholder
+    }
+
+    /// Escribe el fichero si hay marcadores nuevos (en un temporal y renombrando).
+    fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.values)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Bóvedas abiertas en el proceso, por ruta: la API y la vigilancia comparten
+/// la misma y no se pisan al guardar.
+static VAULTS: LazyLock<Mutex<HashMap<PathBuf, Arc<Mutex<PiiVault>>>>> = LazyLock::new(Default::default);
+
+fn shared_vault(path: &Path) -> Result<Arc<Mutex<PiiVault>>> {
+    let mut vaults = VAULTS.lock().unwrap();
+    if let Some(vault) = vaults.get(path) {
+        return Ok(vault.clone());
+    }
+    let vault = Arc::new(Mutex::new(PiiVault::load(path)?));
+    vaults.insert(path.to_path_buf(), vault.clone());
+    Ok(vault)
+}
+
+/// Textos redactados y número de datos sustituidos.
+#[derive(Debug)]
+pub struct Redaction {
+    pub texts: Vec<String>,
+    pub replaced: usize,
+}
+
+/// Etapa de redacción configurada.
+#[derive(Debug, Clone)]
+pub struct PiiRedactor {
+    kinds: Vec<PiiKind>,
+    restore_originals: bool,
+    vault: Arc<Mutex<PiiVault>>,
+}
+
+impl PiiRedactor {
+    /// `None` si la redacción está desactivada (`PII_REDACTION` vacío).
+    pub fn from_config(cfg: &AppConfig) -> Result<Option<Self>> {
+        if cfg.pii_redaction.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            kinds: cfg.pii_redaction.clone(),
+            restore_originals: cfg.pii_restore_originals,
+            vault: shared_vault(Path::new(&cfg.pii_mapping_path))?,
+        }))
+    }
+
+    /// Si el texto guardado en `:Chunk.text` y las respuestas muestran los valores originales.
+    pub fn restores_originals(&self) -> bool {
+        self.restore_originals
+    }
+
+    /// Sustituye los datos personales de cada texto por su marcador.
+    pub fn redact_all(&self, texts: &[String]) -> Result<Redaction> {
+        let mut vault = self.vault.lock().unwrap();
+        let mut replaced = 0;
+        let texts = texts
+            .iter()
+            .map(|text| {
+                let matches = scan(text, &self.kinds);
+                replaced += matches.len();
+                let mut redacted = String::with_capacity(text.len());
+                let mut last = 0;
+                for m in matches {
+                    redacted.push_str(&text[last..m.range.start]);
+                    redacted.push_str(&vault.placeholder(m.kind, &text[m.range.clone()]));
+                    last = m.range.end;
+                }
+                redacted.push_str(&text[last..]);
+                redacted
+            })
+            .collect();
+        vault.save()?;
+        Ok(Redaction { texts, replaced })
+    }
+
+    /// Versión redactada de un único texto (p. ej. la pregunta del usuario).
+    pub fn redact(&self, text: &str) -> Result<String> {
+        let mut redaction = self.redact_all(&[text.to_string()])?;
+        Ok(redaction.texts.remove(0))
+    }
+
+    /// Vuelve a poner los valores originales en lugar de los marcadores conocidos.
+    pub fn restore(&self, text: &str) -> String {
+        let vault = self.vault.lock().unwrap();
+        PLACEHOLDER
+            .replace_all(text, |caps: &regex::Captures| {
+                vault.values.get(&caps[0]).cloned().unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phones(text: &str) -> Vec<&str> {
+        scan(text, &[PiiKind::Phone]).into_iter().map(|m| &text[m.range]).collect()
+    }
+
+    #[test]
+    fn phone_numbers_need_a_phone_shape() {
+        for phone in ["+34 600 123 456", "+1 (555) 123-4567", "555-123-4567", "600123456", "600 12 34 56", "91 123 45 67"] {
+            assert_eq!(phones(&format!("Llamar al {phone} mañana.")), [phone]);
+        }
+        for text in [
+            "Ventas de 2023, 2024 y 2025.",
+            "| 2023 | 1500 | 2300 | 4100 |",
+            "Registrado el 2024-05-17 10:30:00.",
+            "ISBN 978-84-376-0494-7",
+            "Pedido 123456789 enviado.",
+        ] {
+            assert!(phones(text).is_empty(), "{text}: {:?}", phones(text));
+        }
+    }
+
+    #[test]
+    fn the_same_value_always_gets_the_same_placeholder() {
+        let path = std::env::temp_dir().join(format!("nexusrag-pii-{}.json", uuid::Uuid::new_v4()));
+        let redactor = PiiRedactor {
+            kinds: vec![PiiKind::Email, PiiKind::Phone, PiiKind::NationalId],
+            restore_originals: false,
+            vault: shared_vault(&path).unwrap(),
+        };
+
+        let redaction = redactor
+            .redact_all(&["Escribe a Ana@Acme.com o llama al 600 12 34 56.".into(), "ana@acme.com, DNI 12345678Z".into()])
+            .unwrap();
+        assert_eq!(redaction.replaced, 4);
+        assert_eq!(redaction.texts, ["Escribe a [EMAIL_1] o llama al [TELEFONO_1].", "[EMAIL_1], DNI [ID_NACIONAL_1]"]);
+        assert_eq!(redactor.restore(&redaction.texts[0]), "Escribe a Ana@Acme.com o llama al 600 12 34 56.");
+
+        // Los marcadores se conservan entre ejecuciones.
+        let reloaded = PiiVault::load(&path).unwrap();
+        assert_eq!(reloaded.values.get("[TELEFONO_1]").map(String::as_str), Some("600 12 34 56"));
+        assert_eq!(reloaded.counters.get("EMAIL"), Some(&1));
+
+        fs::remove_file(path).unwrap();
+    }
+}
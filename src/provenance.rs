@@ -0,0 +1,148 @@
+//! Procedencia de los chunks: localiza el pasaje original de un `:Chunk` en
+//! su fichero para que la interfaz pueda resaltarlo dentro de su contexto.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use neo4rs::{query, Graph};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{archive, extract, ingest, structured};
+
+/// Caracteres de contexto por defecto a cada lado del pasaje.
+pub const DEFAULT_WINDOW_CHARS: usize = 500;
+
+/// Un chunk con su posición en el origen y, si el fichero sigue igual que
+/// cuando se ingirió, el texto que lo rodea.
+#[derive(Debug, Serialize)]
+pub struct ChunkContext {
+    pub chunk_id: String,
+    pub index: i64,
+    pub text: String,
+    pub section: Option<String>,
+    pub document: Option<String>,
+    pub path: String,
+    pub page_start: Option<i64>,
+    pub page_end: Option<i64>,
+    pub byte_start: Option<i64>,
+    pub byte_end: Option<i64>,
+    pub char_start: Option<i64>,
+    pub char_end: Option<i64>,
+    pub line_start: Option<i64>,
+    pub line_end: Option<i64>,
+    /// `None` si el chunk no tiene posición o el fichero ha cambiado o desaparecido.
+    pub window: Option<SourceWindow>,
+}
+
+/// Pasaje del chunk en el texto de origen, con el texto anterior y posterior.
+#[derive(Debug, Serialize)]
+pub struct SourceWindow {
+    pub before: String,
+    pub passage: String,
+    pub after: String,
+}
+
+/// Recupera un chunk por id con `window_chars` caracteres de contexto a cada lado.
+pub async fn chunk_context(graph: &Graph, chunk_id: &str, window_chars: usize) -> Result<Option<ChunkContext>> {
+    let mut cursor = graph.execute(
+        query(
+            "MATCH (f:File)-[:HAS_DOCUMENT]->(d:Document)-[:HAS_CHUNK]->(c:Chunk {id: $id})
+             RETURN c.index AS index, c.text AS text, c.section AS section, d.title AS document,
+                    f.path AS path, f.content_hash AS content_hash,
+                    c.page_start AS page_start, c.page_end AS page_end,
+                    c.byte_start AS byte_start, c.byte_end AS byte_end,
+                    c.char_start AS char_start, c.char_end AS char_end,
+                    c.line_start AS line_start, c.line_end AS line_end"
+        )
+        .param("id", chunk_id),
+    ).await?;
+    let Some(row) = cursor.next().await? else { return Ok(None) };
+
+    let path: String = row.get("path").ok_or_else(|| anyhow!("Falta campo 'path' en resultado de Neo4j"))?;
+    let content_hash: Option<String> = row.get("content_hash");
+    let byte_start: Option<i64> = row.get("byte_start");
+    let byte_end: Option<i64> = row.get("byte_end");
+
+    let window = match (byte_start, byte_end, content_hash) {
+        (Some(start), Some(end), Some(hash)) => {
+            match source_window(&path, &hash, start as usize..end as usize, window_chars).await {
+                Ok(window) => window,
+                Err(e) => {
+                    warn!("No se pudo leer el contexto del chunk {} en {}: {}", chunk_id, path, e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    Ok(Some(ChunkContext {
+        chunk_id: chunk_id.to_string(),
+        index: row.get("index").unwrap_or_default(),
+        text: row.get("text").unwrap_or_default(),
+        section: row.get("section"),
+        document: row.get("document"),
+        path,
+        page_start: row.get("page_start"),
+        page_end: row.get("page_end"),
+        byte_start,
+        byte_end,
+        char_start: row.get("char_start"),
+        char_end: row.get("char_end"),
+        line_start: row.get("line_start"),
+        line_end: row.get("line_end"),
+        window,
+    }))
+}
+
+/// Vuelve a extraer el texto del fichero y recorta la ventana alrededor del
+/// rango. Devuelve `None` si el contenido ya no es el que se ingirió.
+async fn source_window(
+    path: &str,
+    content_hash: &str,
+    span: std::ops::Range<usize>,
+    window_chars: usize,
+) -> Result<Option<SourceWindow>> {
+    // Los miembros de un archivo comprimido se vuelven a extraer de él.
+    let bytes = if path.contains(archive::SEPARATOR) {
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || archive::read_member(&path)).await??
+    } else {
+        tokio::fs::read(path).await?
+    };
+    if ingest::hash_content(&bytes) != content_hash {
+        return Ok(None);
+    }
+
+    let extension = Path::new(path)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or("")
+        .to_lowercase();
+    // La extracción (p. ej. de un PDF) es costosa y síncrona.
+    let text = tokio::task::spawn_blocking(move || -> Result<String> {
+        if structured::is_structured(&extension) {
+            Ok(String::from_utf8(bytes)?)
+        } else {
+            // Mismo hash que lo ingerido, que ya pasó los límites de descompresión.
+            Ok(extract::extract(&extension, bytes, &mut archive::Budget::new(0, 0))?.text)
+        }
+    })
+    .await??;
+
+    let Some(passage) = text.get(span.clone()) else { return Ok(None) };
+    let before = &text[..span.start];
+    let before_start = match window_chars {
+        0 => before.len(),
+        n => before.char_indices().rev().nth(n - 1).map_or(0, |(i, _)| i),
+    };
+    let after = &text[span.end..];
+    let after_end = after.char_indices().nth(window_chars).map_or(after.len(), |(i, _)| i);
+
+    Ok(Some(SourceWindow {
+        before: before[before_start..].to_string(),
+        passage: passage.to_string(),
+        after: after[..after_end].to_string(),
+    }))
+}
@@ -11,44 +11,103 @@
 use anyhow::Result;
 use chrono::Utc;
 use neo4rs::{query, Graph};
+use serde::Serialize;
 use std::collections::HashSet;
 use uuid::Uuid;
 
 use crate::{
     config::AppConfig,
+    language,
     llm::LlmManager,
     models::QueryNode,
-    vector_store::{self},
+    pii::PiiRedactor,
+    vector_store::{self, ChunkDoc},
 };
 
+/// Procedencia de un chunk usado para responder, para poder verificar la respuesta.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkSource {
+    pub chunk_id: Option<String>,
+    pub document: Option<String>,
+    pub source: Option<String>,
+    pub section: Option<String>,
+    pub language: Option<String>,
+    pub page_start: Option<i64>,
+    pub page_end: Option<i64>,
+    pub score: f64,
+}
+
+/// Resultado de una consulta RAG.
+#[derive(Debug, Clone, Serialize)]
+pub struct RagAnswer {
+    pub answer: String,
+    pub key_entities: Vec<String>,
+    /// Idioma detectado de la pregunta (ISO 639-1), en el que se responde.
+    pub language: Option<String>,
+    /// Chunks recuperados, del más al menos relevante.
+    pub sources: Vec<ChunkSource>,
+}
+
 /// Lanza una consulta RAG:
 /// - Usa `rig-neo4j` para recuperar los `top_k` chunks más relevantes.
 /// - Llama al LLM con el contexto concatenado.
 /// - Registra la consulta en Neo4j.
-/// - MODIFICADO: Devuelve la respuesta y una lista de entidades clave.
+/// - Devuelve la respuesta, las entidades clave y la procedencia (páginas
+///   incluidas) de los chunks usados.
+///
+/// Con `language_filter` (ISO 639-1) sólo se usan chunks en ese idioma. La
+/// respuesta se redacta en el idioma de la pregunta.
 pub async fn rag_query(
     graph: &Graph,
     llm: &LlmManager,
     cfg: &AppConfig,
     question: &str,
     top_k: usize,
-) -> Result<(String, Vec<String>)> {
+    language_filter: Option<&str>,
+) -> Result<RagAnswer> {
+    let question_language = language::detect(question);
+
+    // Con redacción de datos personales, la pregunta sale con los mismos
+    // marcadores que los chunks (y así también casa con ellos).
+    let pii = PiiRedactor::from_config(cfg)?;
+    let model_question = match &pii {
+        Some(pii) => pii.redact(question)?,
+        None => question.to_string(),
+    };
+
     // 1) Buscar top_k chunks vía vector store (puntos de entrada al grafo)
-    let results = vector_store::search_top_chunks(cfg, question, top_k).await?;
+    let results = vector_store::search_top_chunks(cfg, llm, &model_question, top_k, language_filter).await?;
 
     if results.is_empty() {
-        return Ok((
-            "No se encontró información relevante en los documentos para responder a esta pregunta.".to_string(),
-            Vec::new()
-        ));
+        return Ok(RagAnswer {
+            answer: "No se encontró información relevante en los documentos para responder a esta pregunta.".to_string(),
+            key_entities: Vec::new(),
+            language: question_language.map(str::to_string),
+            sources: Vec::new(),
+        });
     }
 
     let mut chunk_texts = Vec::new();
     let mut chunk_ids = Vec::new();
     let mut matches: Vec<(String, f64)> = Vec::new();
+    let mut sources = Vec::new();
 
     for (score, id, doc) in results {
-        chunk_texts.push(doc.text);
+        // La sección y las páginas permiten al LLM citar de dónde sale el texto.
+        match provenance_label(&doc) {
+            Some(label) => chunk_texts.push(format!("[{}]\n{}", label, doc.text)),
+            None => chunk_texts.push(doc.text),
+        }
+        sources.push(ChunkSource {
+            chunk_id: doc.chunk_id,
+            document: doc.document,
+            source: doc.source,
+            section: doc.section,
+            language: doc.language,
+            page_start: doc.page_start,
+            page_end: doc.page_end,
+            score,
+        });
         chunk_ids.push(id.clone());
         matches.push((id, score));
     }
@@ -57,8 +116,18 @@ pub async fn rag_query(
 
     // MEJORA: 2) Expansión en el grafo y construcción de contexto aumentado.
     let (graph_context, key_entities) = build_context_from_graph(graph, &chunk_ids).await?;
+    // Entidades ingeridas antes de que se redactaran las columnas mapeadas.
+    let graph_context = match &pii {
+        Some(pii) if !graph_context.is_empty() => pii.redact(&graph_context)?,
+        _ => graph_context,
+    };
+    // Último cambio en git de los ficheros de los chunks ("¿quién cambió...?").
+    let git_context = match (build_git_context(graph, &chunk_ids).await?, &pii) {
+        (context, Some(pii)) if !context.is_empty() => pii.redact(&context)?,
+        (context, _) => context,
+    };
     
-    let full_context = if graph_context.is_empty() {
+    let mut full_context = if graph_context.is_empty() {
         raw_text_context
     } else {
         format!(
@@ -67,6 +136,9 @@ pub async fn rag_query(
             graph_context
         )
     };
+    if !git_context.is_empty() {
+        full_context.push_str(&format!("\n\n**Historial de Git:**\n{}", git_context));
+    }
 
     // 3) Registrar Query y relaciones MATCHED_CHUNK
     let query_id = Uuid::new_v4().to_string();
@@ -78,11 +150,37 @@ pub async fn rag_query(
     log_query(graph, &query_node, &matches).await?;
 
     // 4) Preguntar al LLM con contexto aumentado
-    let answer = llm.answer_with_context(question, &full_context).await?;
+    let answer = llm
+        .answer_with_context(&model_question, &full_context, question_language.and_then(language::english_name))
+        .await?;
+    let (answer, key_entities) = match &pii {
+        Some(pii) if pii.restores_originals() => {
+            (pii.restore(&answer), key_entities.iter().map(|e| pii.restore(e)).collect())
+        }
+        _ => (answer, key_entities),
+    };
     
-    // 5) Devolver la respuesta y las entidades encontradas
-    let entities_vec = key_entities.into_iter().collect();
-    Ok((answer, entities_vec))
+    // 5) Devolver la respuesta, las entidades encontradas y las fuentes
+    Ok(RagAnswer {
+        answer,
+        key_entities: key_entities.into_iter().collect(),
+        language: question_language.map(str::to_string),
+        sources,
+    })
+}
+
+/// Etiqueta de procedencia de un chunk (`Sección: X | Páginas: 3–4`).
+fn provenance_label(doc: &ChunkDoc) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(section) = &doc.section {
+        parts.push(format!("Sección: {}", section));
+    }
+    match (doc.page_start, doc.page_end) {
+        (Some(start), Some(end)) if start != end => parts.push(format!("Páginas: {}–{}", start, end)),
+        (Some(page), _) => parts.push(format!("Página: {}", page)),
+        _ => {}
+    }
+    (!parts.is_empty()).then(|| parts.join(" | "))
 }
 
 /// MEJORA: A partir de un conjunto de IDs de chunks, explora el grafo de conocimiento
@@ -138,6 +236,34 @@ async fn build_context_from_graph(graph: &Graph, chunk_ids: &[String]) -> Result
     Ok((context, entities))
 }
 
+/// Último commit y autor de los ficheros de los que salen los chunks (el del
+/// archivo comprimido si el fichero es uno de sus miembros), una línea por fichero.
+async fn build_git_context(graph: &Graph, chunk_ids: &[String]) -> Result<String> {
+    let mut cursor = graph.execute(query(
+        "MATCH (file:File)-[:CONTAINS*0..1]->(:File)-[:HAS_DOCUMENT]->(:Document)-[:HAS_CHUNK]->(chunk:Chunk)
+         WHERE elementId(chunk) IN $chunk_ids
+         MATCH (file)-[:LAST_CHANGED_IN]->(c:Commit)-[:AUTHORED_BY]->(p:Person)
+         RETURN DISTINCT file.path AS path, c.hash AS hash, c.message AS message,
+                toString(c.date) AS date, p.name AS author, p.email AS email
+         ORDER BY path"
+    ).param("chunk_ids", chunk_ids.to_vec())).await?;
+
+    let mut lines = Vec::new();
+    while let Some(row) = cursor.next().await? {
+        let (Some(path), Some(hash)) = (row.get::<String>("path"), row.get::<String>("hash")) else { continue };
+        lines.push(format!(
+            "- {}: último cambio de {} <{}> el {} (commit {}: {})",
+            path,
+            row.get::<String>("author").unwrap_or_default(),
+            row.get::<String>("email").unwrap_or_default(),
+            row.get::<String>("date").unwrap_or_default(),
+            &hash[..hash.len().min(12)],
+            row.get::<String>("message").unwrap_or_default(),
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
 async fn log_query(
     graph: &Graph,
     query_node: &QueryNode,
@@ -0,0 +1,277 @@
+//! Ingesta de datos estructurados (CSV, JSON, YAML): cada fila o registro es
+//! un chunk con sus nombres de campo, y las columnas configuradas se
+//! convierten directamente en entidades y relaciones, sin pasar por el LLM.
+
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::llm::{ExtractionResult, JsonExtractedEntity, JsonExtractedRelation};
+
+/// Extensiones que se ingieren registro a registro.
+pub fn is_structured(extension: &str) -> bool {
+    matches!(extension, "csv" | "json" | "jsonl" | "yaml" | "yml")
+}
+
+/// Una fila o registro: pares campo → valor en el orden del fichero.
+#[derive(Debug)]
+pub struct Record {
+    /// Posición del registro en el fichero (1-based), p. ej. `Fila 3`.
+    pub label: String,
+    pub fields: Vec<(String, String)>,
+    /// Bytes del fichero que ocupa el registro, si se conocen (CSV y JSON Lines).
+    pub span: Option<Range<usize>>,
+}
+
+impl Record {
+    /// Texto del chunk: una línea `campo: valor` por campo no vacío.
+    pub fn to_text(&self) -> String {
+        self.fields
+            .iter()
+            .filter(|(_, value)| !value.trim().is_empty())
+            .map(|(field, value)| format!("{field}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn has_field(&self, field: &str) -> bool {
+        self.fields.iter().any(|(name, _)| name.eq_ignore_ascii_case(field))
+    }
+
+    fn value(&self, field: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(field))
+            .map(|(_, value)| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// Lee los registros de un fichero estructurado. Se descartan los vacíos.
+pub fn parse_records(extension: &str, bytes: &[u8]) -> Result<Vec<Record>> {
+    let records = match extension {
+        "csv" => parse_csv(bytes)?,
+        "json" => {
+            let value: Value = serde_json::from_slice(bytes)?;
+            records_from_values(top_level_records(value))
+        }
+        "jsonl" => {
+            let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("el fichero no es texto UTF-8"))?;
+            let mut records = Vec::new();
+            let mut start = 0;
+            for (idx, line) in text.split_inclusive('\n').enumerate() {
+                let span = start..start + line.trim_end().len();
+                start += line.len();
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let value: Value = serde_json::from_str(line)?;
+                let mut fields = Vec::new();
+                flatten_value("", &value, &mut fields);
+                records.push(Record { label: format!("Línea {}", idx + 1), fields, span: Some(span) });
+            }
+            records
+        }
+        "yaml" | "yml" => {
+            // Un fichero YAML puede contener varios documentos separados por `---`.
+            let mut values = Vec::new();
+            for document in serde_yaml::Deserializer::from_slice(bytes) {
+                values.extend(top_level_records(Value::deserialize(document)?));
+            }
+            records_from_values(values)
+        }
+        other => return Err(anyhow!("Formato estructurado no soportado: {other}")),
+    };
+    Ok(records.into_iter().filter(|r| !r.to_text().is_empty()).collect())
+}
+
+fn parse_csv(bytes: &[u8]) -> Result<Vec<Record>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(bytes);
+    let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim().to_string()).collect();
+
+    let mut records = Vec::new();
+    let mut row = csv::StringRecord::new();
+    while reader.read_record(&mut row)? {
+        // Cada fila va desde su posición hasta el final de su última línea.
+        let start = row.position().map_or(0, |p| p.byte() as usize);
+        let end = (reader.position().byte() as usize).clamp(start, bytes.len());
+        let end = start + bytes[start..end].trim_ascii_end().len();
+        let fields = row
+            .iter()
+            .enumerate()
+            .map(|(col, value)| {
+                let name = headers
+                    .get(col)
+                    .filter(|h| !h.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| format!("columna_{}", col + 1));
+                (name, value.to_string())
+            })
+            .collect();
+        records.push(Record { label: format!("Fila {}", records.len() + 1), fields, span: Some(start..end) });
+    }
+    Ok(records)
+}
+
+/// Un array en la raíz es una lista de registros; cualquier otro valor es un único registro.
+fn top_level_records(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        Value::Null => Vec::new(),
+        other => vec![other],
+    }
+}
+
+fn records_from_values(values: Vec<Value>) -> Vec<Record> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let mut fields = Vec::new();
+            flatten_value("", &value, &mut fields);
+            Record { label: format!("Registro {}", idx + 1), fields, span: None }
+        })
+        .collect()
+}
+
+/// Aplana objetos anidados con claves `a.b.c`. Las listas de escalares se unen
+/// con comas; las de objetos se indexan (`items[0].nombre`).
+fn flatten_value(prefix: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    let field_name = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{prefix}.{key}") };
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten_value(&field_name(key), value, fields);
+            }
+        }
+        Value::Array(items) if items.iter().all(|item| !item.is_object() && !item.is_array()) => {
+            let joined = items.iter().map(scalar_to_string).collect::<Vec<_>>().join(", ");
+            fields.push((prefix.to_string(), joined));
+        }
+        Value::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                flatten_value(&format!("{prefix}[{idx}]"), item, fields);
+            }
+        }
+        scalar => {
+            let name = if prefix.is_empty() { "valor".to_string() } else { prefix.to_string() };
+            fields.push((name, scalar_to_string(scalar)));
+        }
+    }
+}
+
+fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Columnas que se convierten en entidades y relaciones sin usar el LLM.
+#[derive(Debug, Clone, Default)]
+pub struct StructuredMapping {
+    /// Campo → etiqueta de la entidad (p. ej. `proveedor` → `Organization`).
+    pub entity_fields: Vec<(String, String)>,
+    /// (campo sujeto, predicado, campo objeto).
+    pub relations: Vec<(String, String, String)>,
+}
+
+impl StructuredMapping {
+    /// Si algún registro del fichero tiene alguna de las columnas mapeadas. Si
+    /// no, el mapeo no dice nada de ese fichero y sus entidades las extrae el LLM.
+    pub fn applies_to(&self, records: &[Record]) -> bool {
+        self.entity_fields
+            .iter()
+            .any(|(field, _)| records.iter().any(|record| record.has_field(field)))
+    }
+
+    /// Entidades y relaciones de un registro según el mapeo configurado.
+    pub fn extract(&self, record: &Record) -> ExtractionResult {
+        let entities = self
+            .entity_fields
+            .iter()
+            .filter_map(|(field, label)| {
+                record.value(field).map(|value| JsonExtractedEntity {
+                    id: value.to_string(),
+                    label: label.clone(),
+                })
+            })
+            .collect();
+        let relations = self
+            .relations
+            .iter()
+            .filter_map(|(subject, predicate, object)| {
+                Some(JsonExtractedRelation {
+                    subject: record.value(subject)?.to_string(),
+                    predicate: predicate.clone(),
+                    object: record.value(object)?.to_string(),
+                })
+            })
+            .collect();
+        ExtractionResult { entities, relations }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_rows_keep_their_byte_spans() {
+        let csv = "nombre,empresa\nAna,Acme\n\"Luis\nPérez\",Globex\n";
+        let records = parse_records("csv", csv.as_bytes()).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].label, "Fila 1");
+        assert_eq!(&csv[records[0].span.clone().unwrap()], "Ana,Acme");
+        // Una fila con un salto de línea entre comillas ocupa dos líneas.
+        assert_eq!(&csv[records[1].span.clone().unwrap()], "\"Luis\nPérez\",Globex");
+        assert_eq!(records[1].to_text(), "nombre: Luis\nPérez\nempresa: Globex");
+    }
+
+    #[test]
+    fn json_documents_are_flattened_into_records() {
+        let json = r#"[{"dirección": {"ciudad": "Madrid"}, "nombre": "Ana", "tags": ["a", "b"]}, {}, {"items": [{"id": 1}]}]"#;
+        let records = parse_records("json", json.as_bytes()).unwrap();
+
+        // El registro vacío se descarta.
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].to_text(), "dirección.ciudad: Madrid\nnombre: Ana\ntags: a, b");
+        assert_eq!(records[1].to_text(), "items[0].id: 1");
+        assert!(records[0].span.is_none());
+
+        // Un objeto en la raíz es un único registro.
+        let single = parse_records("json", br#"{"clave": "valor"}"#).unwrap();
+        assert_eq!(single.len(), 1);
+
+        let jsonl = "{\"a\": 1}\n\n{\"a\": 2}\n";
+        let lines = parse_records("jsonl", jsonl.as_bytes()).unwrap();
+        assert_eq!(lines[1].label, "Línea 3");
+        assert_eq!(&jsonl[lines[1].span.clone().unwrap()], "{\"a\": 2}");
+    }
+
+    #[test]
+    fn mapping_applies_only_to_files_with_mapped_columns() {
+        let mapping = StructuredMapping {
+            entity_fields: vec![("empresa".into(), "Organization".into()), ("nombre".into(), "Person".into())],
+            relations: vec![("nombre".into(), "WORKS_AT".into(), "empresa".into())],
+        };
+        let people = parse_records("csv", b"Nombre,Empresa\nAna,Acme\nLuis,\n").unwrap();
+        let other = parse_records("csv", b"producto,precio\nlibro,10\n").unwrap();
+
+        assert!(mapping.applies_to(&people));
+        assert!(!mapping.applies_to(&other));
+
+        let first = mapping.extract(&people[0]);
+        assert_eq!(first.entities.len(), 2);
+        assert_eq!(first.relations[0].subject, "Ana");
+        assert_eq!(first.relations[0].object, "Acme");
+        // Sin empresa no hay relación, pero sí la persona.
+        let second = mapping.extract(&people[1]);
+        assert_eq!(second.entities.len(), 1);
+        assert!(second.relations.is_empty());
+    }
+}
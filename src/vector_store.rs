@@ -2,14 +2,15 @@
 //!
 //! API pública:
 //!   - `ensure_chunk_vector_index(&AppConfig)`
-//!   - `search_top_chunks(&AppConfig, &str, usize)`.
+//!   - `search_top_chunks(&AppConfig, &LlmManager, &str, usize, Option<&str>)`.
 
 use anyhow::{anyhow, Result};
 use neo4rs::query;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::config::AppConfig;
+use crate::llm::LlmManager;
 use crate::neo4j_client;
 
 /// Documento mínimo que representa un :Chunk con texto y vector.
@@ -17,11 +18,27 @@ use crate::neo4j_client;
 pub struct ChunkDoc {
     // CORREGIDO: El ID que se devuelve es el elementId, no una propiedad.
     // pub id: String, 
+    /// Texto tal como puede enviarse al LLM (con marcadores si se redactaron datos personales).
     pub text: String,
     pub embedding: Vec<f64>,
+    /// Sección (ruta de encabezados o elemento de código) de la que procede.
+    pub section: Option<String>,
+    /// Idioma (ISO 639-1) del chunk.
+    pub language: Option<String>,
+    /// Páginas del original (PDF) que cubre el chunk.
+    pub page_start: Option<i64>,
+    pub page_end: Option<i64>,
+    /// Id (propiedad `id`) del `:Chunk`, para pedir su contexto de origen.
+    pub chunk_id: Option<String>,
+    /// Título y ruta del `:Document` al que pertenece.
+    pub document: Option<String>,
+    pub source: Option<String>,
 }
 
-/// Garantiza que el índice vectorial sobre `:Chunk(embedding)` exista.
+/// Garantiza que el índice vectorial sobre `:Chunk(embedding)` exista con las
+/// dimensiones configuradas. Si se creó con otras (se cambió el modelo de
+/// embeddings), se recrea; la siguiente ingesta recalcula los chunks, porque
+/// la firma de embeddings de sus `:File` ya no coincide.
 pub async fn ensure_chunk_vector_index(cfg: &AppConfig) -> Result<()> {
     let graph = neo4j_client::connect_from_config(cfg).await?;
     let index_name = "chunkEmbeddingIndex";
@@ -29,14 +46,26 @@ pub async fn ensure_chunk_vector_index(cfg: &AppConfig) -> Result<()> {
     // ¿Ya existe el índice? Usamos la sintaxis moderna SHOW VECTOR INDEXES.
     let mut cursor = graph
         .execute(
-            query("SHOW VECTOR INDEXES YIELD name WHERE name = $name RETURN name")
+            query(
+                "SHOW VECTOR INDEXES YIELD name, options WHERE name = $name
+                 RETURN options.indexConfig['vector.dimensions'] AS dimensions"
+            )
             .param("name", index_name),
         )
         .await?;
 
-    if cursor.next().await?.is_some() {
-        info!("Índice vectorial '{index_name}' ya existe.");
-        return Ok(());
+    if let Some(row) = cursor.next().await? {
+        let dimensions: Option<i64> = row.get("dimensions");
+        if dimensions == Some(cfg.llm_embedding_dimensions as i64) {
+            info!("Índice vectorial '{index_name}' ya existe.");
+            return Ok(());
+        }
+        warn!(
+            "El índice vectorial '{index_name}' tiene {:?} dimensiones y los embeddings configurados {}: se recrea. \
+             Vuelve a ingerir los documentos para recalcular sus chunks.",
+            dimensions, cfg.llm_embedding_dimensions
+        );
+        graph.run(query(&format!("DROP INDEX {index_name} IF EXISTS"))).await?;
     }
 
     // Crear índice vectorial para :Chunk(embedding)
@@ -47,11 +76,12 @@ FOR (c:Chunk)
 ON (c.embedding)
 OPTIONS {{
   indexConfig: {{
-    `vector.dimensions`: 1536,
+    `vector.dimensions`: {dimensions},
     `vector.similarity_function`: 'cosine'
   }}
 }}",
-        index_name = index_name
+        index_name = index_name,
+        dimensions = cfg.llm_embedding_dimensions
     );
 
     graph.run(query(&cypher)).await?;
@@ -60,27 +90,22 @@ OPTIONS {{
     Ok(())
 }
 
+/// Candidatos que se piden al índice por cada resultado cuando se filtra por
+/// idioma, ya que el filtro se aplica después de la búsqueda vectorial.
+const LANGUAGE_FILTER_OVERFETCH: usize = 10;
+
 /// Realiza una búsqueda vectorial (semantic search) sobre los embeddings
-/// almacenados en `:Chunk(embedding)`.
+/// almacenados en `:Chunk(embedding)`. Con `language` (ISO 639-1) sólo se
+/// devuelven chunks en ese idioma.
 pub async fn search_top_chunks(
     cfg: &AppConfig,
+    llm: &LlmManager,
     query_text: &str,
     top_k: usize,
+    language: Option<&str>,
 ) -> Result<Vec<(f64, String, ChunkDoc)>> {
-    use rig::providers::openai::{self, TEXT_EMBEDDING_3_SMALL};
-    use rig::client::EmbeddingsClient as _;
-    use rig::embeddings::EmbeddingModel as _;
-
-    if !matches!(cfg.llm_provider, crate::config::LlmProvider::OpenAI) {
-        return Err(anyhow!( "search_top_chunks sólo está implementado para OpenAI por ahora"));
-    }
-
-    // 1) Embedding de la query
-    let client = openai::Client::from_env();
-    let model_name = if cfg.llm_embedding_model.is_empty() { TEXT_EMBEDDING_3_SMALL } else { cfg.llm_embedding_model.as_str() };
-    let embedding_model = client.embedding_model(model_name);
-    let embeddings = embedding_model.embed_texts(vec![query_text.to_string()]).await?;
-    let query_vec = embeddings.get(0).map(|e| e.vec.clone()).ok_or_else(|| anyhow!("No se pudo generar embedding de la query"))?;
+    // 1) Embedding de la query, con el mismo proveedor y modelo que los chunks
+    let query_vec = llm.embed_query(query_text).await?;
 
     // 2) Vector search en Neo4j
     let graph = neo4j_client::connect_from_config(cfg).await?;
@@ -88,11 +113,18 @@ pub async fn search_top_chunks(
         query(
             "CALL db.index.vector.queryNodes($index_name, $k, $embedding)
              YIELD node, score
-             RETURN elementId(node) AS id, score, node.text AS text, node.embedding AS embedding
+             WHERE $language = '' OR node.language = $language
+             WITH node, score ORDER BY score DESC LIMIT $top_k
+             OPTIONAL MATCH (d:Document)-[:HAS_CHUNK]->(node)
+             RETURN elementId(node) AS id, node.id AS chunk_id, score, coalesce(node.redacted_text, node.text) AS text, node.embedding AS embedding,
+                    node.section AS section, node.language AS language, node.page_start AS page_start, node.page_end AS page_end,
+                    d.title AS document, d.source AS source
              ORDER BY score DESC"
         )
         .param("index_name", "chunkEmbeddingIndex")
-        .param("k", top_k as i64)
+        .param("k", (if language.is_some() { top_k * LANGUAGE_FILTER_OVERFETCH } else { top_k }) as i64)
+        .param("top_k", top_k as i64)
+        .param("language", language.unwrap_or_default())
         .param("embedding", query_vec.clone()),
     ).await?;
 
@@ -104,7 +136,15 @@ pub async fn search_top_chunks(
         let text: String = row.get("text").ok_or_else(|| anyhow!("Falta campo 'text' en resultado de Neo4j"))?;
         let embedding: Vec<f64> = row.get("embedding").ok_or_else(|| anyhow!("Falta campo 'embedding' en resultado de Neo4j"))?;
 
-        let doc = ChunkDoc { text, embedding };
+        let section: Option<String> = row.get("section");
+        let language: Option<String> = row.get("language");
+        let page_start: Option<i64> = row.get("page_start");
+        let page_end: Option<i64> = row.get("page_end");
+        let chunk_id: Option<String> = row.get("chunk_id");
+        let document: Option<String> = row.get("document");
+        let source: Option<String> = row.get("source");
+
+        let doc = ChunkDoc { text, embedding, section, language, page_start, page_end, chunk_id, document, source };
         output.push((score, id, doc));
     }
 
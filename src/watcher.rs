@@ -0,0 +1,202 @@
+//! Modo vigilancia: observa el directorio seleccionado y mantiene el grafo
+//! sincronizado re-ingiriendo o podando sólo los ficheros afectados.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{EventKind, RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer, RecommendedCache,
+};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::{app_state::AppState, ingest};
+
+/// Tiempo que se agrupan los eventos del sistema de archivos antes de procesarlos.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Espera antes de reintentar un lote aplazado por otro trabajo de ingesta.
+const BUSY_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Vigilancia activa sobre un directorio. Al soltarla se detiene el observador.
+pub struct WatchHandle {
+    pub root: PathBuf,
+    _debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+    control: Arc<Mutex<WatchControl>>,
+}
+
+/// Estado compartido entre la vigilancia y su tarea.
+#[derive(Default)]
+struct WatchControl {
+    stopped: bool,
+    /// Bandera de cancelación del lote en curso, si lo hay.
+    batch_cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Drop for WatchHandle {
+    /// La tarea no se aborta: el lote en curso se cancela y termina cerrando su
+    /// trabajo, y al soltar el observador el bucle acaba solo.
+    fn drop(&mut self) {
+        let mut control = self.control.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        control.stopped = true;
+        if let Some(cancel) = &control.batch_cancel {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Empieza a vigilar `root` de forma recursiva. Los eventos de creación,
+/// modificación, borrado y renombrado se agrupan y se sincronizan con
+/// `ingest::sync_paths`.
+pub fn start_watch(state: AppState, root: PathBuf) -> Result<WatchHandle> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<DebounceEventResult>();
+
+    let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, None, move |result: DebounceEventResult| {
+        let _ = tx.send(result);
+    })?;
+    debouncer.watch(&root, RecursiveMode::Recursive)?;
+
+    let control = Arc::new(Mutex::new(WatchControl::default()));
+    let task_control = control.clone();
+    let task_root = root.clone();
+    tokio::spawn(async move {
+        // Rutas pendientes; se conservan si hay que aplazar el lote.
+        let mut paths = BTreeSet::new();
+        loop {
+            if paths.is_empty() {
+                let Some(result) = rx.recv().await else { break };
+                collect_paths(result, &mut paths);
+            } else {
+                // Lote aplazado: se reintenta aunque no lleguen eventos nuevos.
+                match tokio::time::timeout(BUSY_RETRY_INTERVAL, rx.recv()).await {
+                    Ok(Some(result)) => collect_paths(result, &mut paths),
+                    Ok(None) => break,
+                    Err(_) => {}
+                }
+            }
+            // Agrupamos también los lotes que hayan llegado mientras tanto.
+            while let Ok(result) = rx.try_recv() {
+                collect_paths(result, &mut paths);
+            }
+            if paths.is_empty() {
+                continue;
+            }
+            if sync_batch(&state, &task_control, &task_root, paths.iter().cloned().collect()).await {
+                paths.clear();
+            }
+            if task_control.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).stopped {
+                break;
+            }
+        }
+    });
+
+    info!("👀 Vigilando cambios en {}", root.display());
+    Ok(WatchHandle {
+        root,
+        _debouncer: debouncer,
+        control,
+    })
+}
+
+/// Extrae las rutas afectadas de un lote de eventos, ignorando los accesos.
+fn collect_paths(result: DebounceEventResult, paths: &mut BTreeSet<PathBuf>) {
+    match result {
+        Ok(events) => {
+            for event in events {
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                ) {
+                    paths.extend(event.paths.iter().cloned());
+                }
+            }
+        }
+        Err(errors) => {
+            for err in errors {
+                warn!("Error del observador de ficheros: {}", err);
+            }
+        }
+    }
+}
+
+/// Sincroniza un lote como un trabajo más del registro, para no ingerir los
+/// mismos ficheros a la vez que `/api/ingest` ni pisar su estado. Devuelve
+/// `false` si había otro trabajo en marcha y el lote se ha aplazado.
+async fn sync_batch(state: &AppState, control: &Mutex<WatchControl>, root: &Path, paths: Vec<PathBuf>) -> bool {
+    // El trabajo se registra con `control` bloqueado: o la vigilancia ya se ha
+    // detenido y el lote se descarta, o `Drop` verá su bandera de cancelación.
+    let (job_id, cancel) = {
+        let mut control = control.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if control.stopped {
+            return true;
+        }
+        match state.jobs.start(root.to_path_buf()) {
+            Ok((job_id, cancel)) => {
+                control.batch_cancel = Some(cancel.clone());
+                (job_id, cancel)
+            }
+            Err(running_id) => {
+                info!(
+                    "[Vigilancia] Trabajo {} en curso: se aplaza la sincronización de {} ruta(s).",
+                    running_id,
+                    paths.len()
+                );
+                return false;
+            }
+        }
+    };
+
+    {
+        let mut status = state.status.lock().unwrap();
+        status.is_busy = true;
+        status.message = format!(
+            "[Vigilancia] Sincronizando {} ruta(s) modificada(s) en {}...",
+            paths.len(),
+            root.display()
+        );
+        status.progress = 0.0;
+    }
+
+    // En su propia tarea: si entra en pánico, el trabajo se cierra igualmente.
+    let task_state = state.clone();
+    let task_root = root.to_path_buf();
+    let task_cancel = cancel.clone();
+    let result = tokio::spawn(async move {
+        let state = task_state;
+        let options = ingest::IngestOptions::from_config(&state.config, state.llm_permits.clone())?;
+        ingest::sync_paths(&state.graph, &state.llm_manager, &task_root, &paths, &options, state.status.clone(), &task_cancel)
+            .await
+    })
+    .await
+    .map_err(|e| anyhow!("La sincronización terminó de forma inesperada: {}", e))
+    .and_then(|result| result);
+    state.jobs.finish(&job_id, &result);
+    control.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).batch_cancel = None;
+
+    let mut status = state.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    status.is_busy = false;
+    status.progress = 0.0;
+    match result {
+        Ok(summary) if cancel.load(Ordering::Relaxed) => {
+            status.message = format!("[Vigilancia] Sincronización cancelada. {}", summary);
+        }
+        Ok(summary) => {
+            status.message = format!("[Vigilancia] Cambios sincronizados. {}", summary);
+        }
+        Err(err) => {
+            status.message = format!("[Vigilancia] Error sincronizando cambios: {}", err);
+            error!("Error en la sincronización de la vigilancia: {}", err);
+        }
+    }
+    true
+}